@@ -16,6 +16,8 @@ use serde::{Deserialize, Serialize};
 use strum::{EnumIter, IntoEnumIterator};
 use thiserror::Error;
 
+use crate::raws::RAWS;
+
 #[derive(Error, Debug)]
 pub enum SoundError {
     #[error("Sound not loaded: {0}")]
@@ -85,7 +87,7 @@ impl SoundResource {
     }
 
     //TODO rename to effect
-    pub fn load_audio(&mut self, url: &'static str) {
+    pub fn load_audio(&mut self, url: &str) {
         let asset = Asset::Effect(url.into());
         if self.loading.get(&asset).is_some() {
             // We're already trying to load it
@@ -95,12 +97,14 @@ impl SoundResource {
             console::log("Sound already loaded");
             return;
         }
-        console::log(format!("Loading sound: {}", url));
+        // Played by its usual path, even if a mod swapped the file
+        let file = RAWS.lock().unwrap().sound_file(url);
+        console::log(format!("Loading sound: {}", file));
 
         self.loading.insert(asset.clone());
 
         let parc = self.tx.clone();
-        load_audio_data(url, SoundSettings::default(), move |s| {
+        load_audio_data(file, SoundSettings::default(), move |s| {
             let mut pushed = false;
             while !pushed {
                 match parc.lock() {
@@ -118,6 +122,17 @@ impl SoundResource {
         });
     }
 
+    /// Loads every sound the raws name, so sounds added by mods are there to
+    /// play too. Music is left to `load_music`.
+    pub fn load_raws_sounds(&mut self) {
+        let sounds = RAWS.lock().unwrap().sounds();
+        for url in sounds.iter() {
+            if !Music::iter().any(|music| music.filename() == url) {
+                self.load_audio(url);
+            }
+        }
+    }
+
     pub fn play_sound(&mut self, url: &str, settings: InstanceSettings) -> Result<(), SoundError> {
         let asset = Asset::Effect(url.into());
         if let Some(sound) = self.sounds.get_mut(url) {
@@ -148,7 +163,7 @@ impl SoundResource {
 
     pub fn load_music(&mut self) {
         for music in Music::iter() {
-            let file = music.filename();
+            let file = RAWS.lock().unwrap().sound_file(music.filename());
             let asset = Asset::Music(music);
             if self.loading.get(&asset).is_some() {
                 // We're already trying to load it
@@ -200,7 +215,8 @@ impl SoundResource {
             next.play(
                 InstanceSettings::default()
                     .loop_start(0f64)
-                    .fade_in_tween(tween),
+                    .fade_in_tween(tween)
+                    .playback_rate(music.playback_rate()),
             )?;
         }
 
@@ -249,14 +265,38 @@ pub enum Asset {
 #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug, EnumIter)]
 pub enum Music {
     Abyss,
+    Boss,
     GameOver,
 }
 
 impl Music {
+    /// The sound each track is known by. The raws' `sounds` decide which file
+    /// actually plays, so a mod can replace any of them.
     pub fn filename(&self) -> &'static str {
         match self {
             Music::Abyss => "assets/audio/roguelike_abyss.ogg",
-            Music::GameOver => "assets/audio/roguelike_abyss.ogg", // TODO make replacement
+            Music::Boss => "assets/audio/roguelike_boss.ogg",
+            Music::GameOver => "assets/audio/roguelike_game_over.ogg",
+        }
+    }
+
+    /// How fast a track plays when it has no file of its own and borrows the
+    /// abyss track, so the change of music can still be heard
+    fn stand_in_rate(&self) -> f64 {
+        match self {
+            Music::Abyss => 1.0,
+            Music::Boss => 1.3,
+            Music::GameOver => 0.7,
+        }
+    }
+
+    fn playback_rate(&self) -> f64 {
+        let raws = RAWS.lock().unwrap();
+        let file = raws.sound_file(self.filename());
+        if *self != Music::Abyss && file == raws.sound_file(Music::Abyss.filename()) {
+            self.stand_in_rate()
+        } else {
+            1.0
         }
     }
 }
@@ -267,12 +307,8 @@ pub struct DesireMusic {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn load_audio_data(
-    uri: &'static str,
-    settings: SoundSettings,
-    callback: impl FnOnce(Sound) + 'static,
-) {
-    let sound = Sound::from_file(uri, settings)
+fn load_audio_data(uri: String, settings: SoundSettings, callback: impl FnOnce(Sound) + 'static) {
+    let sound = Sound::from_file(&uri, settings)
         .expect(format!("Unable to read sound file {}", uri).as_str());
     callback(sound)
 }
@@ -287,11 +323,7 @@ use wasm_bindgen_futures::JsFuture;
 use web_sys::{AudioBuffer, AudioContext, Request, RequestInit, RequestMode, Response};
 
 #[cfg(target_arch = "wasm32")]
-fn load_audio_data(
-    url: &'static str,
-    settings: SoundSettings,
-    callback: impl FnOnce(Sound) + 'static,
-) {
+fn load_audio_data(url: String, settings: SoundSettings, callback: impl FnOnce(Sound) + 'static) {
     std::mem::drop(wasm_bindgen_futures::future_to_promise(
         load_audio_data_async(url, settings, callback),
     ));
@@ -299,7 +331,7 @@ fn load_audio_data(
 
 #[cfg(target_arch = "wasm32")]
 async fn load_audio_data_async(
-    url: &'static str,
+    url: String,
     settings: SoundSettings,
     callback: impl FnOnce(Sound) + 'static,
 ) -> Result<JsValue, JsValue> {
@@ -0,0 +1,101 @@
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+use crate::{
+    components::{Hidden, Position, Renderable},
+    map::{tile_glyph, Map},
+    DebugOptions,
+};
+
+/// The part of the screen the map is drawn on, the rest belongs to the UI
+pub const VIEWPORT_WIDTH: i32 = 80;
+pub const VIEWPORT_HEIGHT: i32 = 43;
+
+/// The world position shown in the top left corner of the viewport
+fn viewport_origin(ecs: &World) -> Point {
+    let player_pos = ecs.fetch::<Point>();
+    Point::new(
+        player_pos.x - VIEWPORT_WIDTH / 2,
+        player_pos.y - VIEWPORT_HEIGHT / 2,
+    )
+}
+
+pub fn screen_to_world(ecs: &World, screen_pos: Point) -> Point {
+    let origin = viewport_origin(ecs);
+    Point::new(screen_pos.x + origin.x, screen_pos.y + origin.y)
+}
+
+pub fn world_to_screen(ecs: &World, world_pos: Point) -> Point {
+    let origin = viewport_origin(ecs);
+    Point::new(world_pos.x - origin.x, world_pos.y - origin.y)
+}
+
+pub fn is_in_viewport(screen_pos: Point) -> bool {
+    screen_pos.x >= 0
+        && screen_pos.x < VIEWPORT_WIDTH
+        && screen_pos.y >= 0
+        && screen_pos.y < VIEWPORT_HEIGHT
+}
+
+/// Draws the part of the map around the player, and everything on it
+pub fn render_camera(ecs: &World, ctx: &mut BTerm) {
+    let map = ecs.fetch::<Map>();
+    let debug = ecs.fetch::<DebugOptions>();
+    let origin = viewport_origin(ecs);
+
+    render_tiles(&map, origin, debug.fog_off, ctx);
+
+    // Render monsters and objects
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let mut data = (&entities, &positions, &renderables)
+        .join()
+        .filter(|(entity, _, _)| debug.reveal_hidden || hidden.get(*entity).is_none())
+        .collect::<Vec<_>>();
+    data.sort_by(|&a, &b| b.2.render_order.cmp(&a.2.render_order));
+    for (_entity, pos, render) in data.iter() {
+        let screen_pos = Point::new(pos.x - origin.x, pos.y - origin.y);
+        if !is_in_viewport(screen_pos) {
+            continue;
+        }
+        let idx = map.xy_idx(pos.x, pos.y);
+        if debug.fog_off || map.visible_tiles[idx] {
+            ctx.set(
+                screen_pos.x,
+                screen_pos.y,
+                render.fg,
+                render.bg,
+                render.glyph,
+            );
+        }
+    }
+}
+
+/// Draws a whole map, centred in the viewport, e.g. a snapshot from map generation
+pub fn render_debug_map(map: &Map, ctx: &mut BTerm) {
+    let origin = Point::new(
+        map.width / 2 - VIEWPORT_WIDTH / 2,
+        map.height / 2 - VIEWPORT_HEIGHT / 2,
+    );
+    render_tiles(map, origin, true, ctx);
+}
+
+fn render_tiles(map: &Map, origin: Point, fog_off: bool, ctx: &mut BTerm) {
+    for screen_y in 0..VIEWPORT_HEIGHT {
+        for screen_x in 0..VIEWPORT_WIDTH {
+            let x = screen_x + origin.x;
+            let y = screen_y + origin.y;
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+            let idx = map.xy_idx(x, y);
+            if fog_off || map.revealed_tiles[idx] {
+                let (glyph, fg, bg) = tile_glyph(idx, map);
+                ctx.set(screen_x, screen_y, fg, bg, glyph);
+            }
+        }
+    }
+}
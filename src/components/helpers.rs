@@ -1,4 +1,4 @@
-use super::{GivenName, Name};
+use super::{GivenName, Name, Stack, Weight};
 
 pub fn named(name: Option<&Name>, given_name: Option<&GivenName>) -> String {
     if let Some(name) = name {
@@ -11,3 +11,18 @@ pub fn named(name: Option<&Name>, given_name: Option<&GivenName>) -> String {
         "Unknown".into()
     }
 }
+
+/// Adds how many there are to an item's name, if there's more than one
+pub fn counted(name: String, stack: Option<&Stack>) -> String {
+    match stack {
+        Some(stack) if stack.count > 1 => format!("{} (x{})", name, stack.count),
+        _ => name,
+    }
+}
+
+/// What an item weighs, all of the stack if it is one
+pub fn stack_weight(weight: Option<&Weight>, stack: Option<&Stack>) -> f32 {
+    weight.map_or(0.0, |weight| {
+        weight.weight * stack.map_or(1, |stack| stack.count) as f32
+    })
+}
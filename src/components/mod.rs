@@ -21,6 +21,14 @@ pub struct Position {
     pub y: i32,
 }
 
+/// Where an entity on a level the player isn't currently on is
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct OtherLevelPosition {
+    pub x: i32,
+    pub y: i32,
+    pub depth: i32,
+}
+
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct Renderable {
     pub glyph: FontCharType,
@@ -55,15 +63,46 @@ pub enum MonsterType {
     Goblin,
 }
 
+impl MonsterType {
+    /// Orcs know how to work a door handle, goblins wait behind it
+    pub fn can_open_doors(self) -> bool {
+        match self {
+            MonsterType::Orc => true,
+            MonsterType::Goblin => false,
+        }
+    }
+}
+
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct Monster {
     pub monster_type: MonsterType,
     pub seen_player: bool,
 }
 
+/// A monster there's only one of, which gets a fanfare when first seen and
+/// when slain
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Unique {
+    pub spotted: bool,
+}
+
+/// The loot table a monster rolls on when it dies
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct DropsLoot {
+    pub table: String,
+}
+
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
 pub struct BlocksTile {}
 
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct BlocksVisibility {}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Door {
+    pub open: bool,
+}
+
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct CombatStats {
     pub max_hp: i32,
@@ -182,9 +221,138 @@ pub struct HungerClock {
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
 pub struct ProvidesFood {}
 
+/// What's left of a monster. It rots away over time, and eating it can make
+/// you ill.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Corpse {
+    pub turns_left: i32,
+    /// Damage done to whoever eats it, fresh or not
+    pub poison: i32,
+}
+
+impl Corpse {
+    /// With this many turns left it has gone off
+    pub const ROTTEN_TURNS: i32 = 100;
+    /// Damage done by eating it once it has gone off, on top of any poison
+    pub const ROT_DAMAGE: i32 = 3;
+
+    pub fn is_rotten(&self) -> bool {
+        self.turns_left <= Corpse::ROTTEN_TURNS
+    }
+}
+
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
 pub struct MagicMapper {}
 
+/// Identifies what the user is carrying
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Identifier {}
+
+/// What a shop sells an item for
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct ItemValue {
+    pub value: i32,
+}
+
+/// A pile of gold lying around, which goes into the wallet of whoever picks it up
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Gold {
+    pub amount: i32,
+}
+
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Wallet {
+    pub gold: i32,
+}
+
+/// Trades the items they carry for gold, and buys the player's
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Vendor {}
+
+/// How heavy an item is, each, if it's a stack
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Weight {
+    pub weight: f32,
+}
+
+/// How much an entity is carrying, and how much it can carry before it's
+/// overburdened. Kept up to date by the `EncumbranceSystem`.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Burden {
+    pub carried: f32,
+    pub capacity: f32,
+}
+
+impl Burden {
+    const BASE_CAPACITY: f32 = 20.0;
+    const CAPACITY_PER_POWER: f32 = 5.0;
+    /// Nothing more can be picked up once the load would go past this many
+    /// times the capacity
+    const HARD_CAP: f32 = 1.5;
+
+    pub fn capacity_for(stats: &CombatStats) -> f32 {
+        Burden::BASE_CAPACITY + stats.power as f32 * Burden::CAPACITY_PER_POWER
+    }
+
+    pub fn is_overburdened(&self) -> bool {
+        self.carried > self.capacity
+    }
+
+    /// Whether `weight` more can be carried at all
+    pub fn can_carry(&self, weight: f32) -> bool {
+        self.carried + weight <= self.capacity * Burden::HARD_CAP
+    }
+}
+
+/// A pile of identical items, carried and used as one entity
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Stack {
+    pub count: i32,
+}
+
+/// Equipment that can't be taken off once it's on. Nobody knows until then.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Cursed {
+    pub known: bool,
+}
+
+/// Lifts the curses on what the user is wearing
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct CurseRemover {}
+
+/// Lights up the tiles it can see, fading out towards the edge of its range
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct LightSource {
+    pub colour: RGB,
+    pub range: i32,
+}
+
+/// A light source that burns fuel, and dims as it runs out
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Lantern {
+    pub fuel: i32,
+    pub max_fuel: i32,
+}
+
+impl Lantern {
+    /// Below this much fuel the lantern starts to dim
+    pub const LOW_FUEL: i32 = 100;
+
+    /// How far the lantern reaches, given how far its light goes when full
+    pub fn range(&self, full_range: i32) -> i32 {
+        if self.fuel >= Lantern::LOW_FUEL {
+            full_range
+        } else {
+            (full_range * self.fuel + Lantern::LOW_FUEL - 1) / Lantern::LOW_FUEL
+        }
+    }
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct ProvidesFuel {
+    pub amount: i32,
+}
+
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
 pub struct Animation {
     pub duration_ms: f32,
@@ -198,7 +366,9 @@ pub struct Hidden {}
 pub struct EntryTrigger {}
 
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
-pub struct EntityMoved {}
+pub struct EntityMoved {
+    pub from: Point,
+}
 
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
 pub struct SingleActivation {}
@@ -206,6 +376,10 @@ pub struct SingleActivation {}
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct SerializationHelper {
     pub map: crate::map::Map,
+    pub dungeon: crate::dungeon::MasterDungeonMap,
+    pub rng: crate::rng::GameRng,
+    /// The mods active when the game was saved
+    pub mods: Vec<String>,
 }
 
 pub struct SerializeMe;
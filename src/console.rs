@@ -1,10 +1,20 @@
-use std::cmp::{max, min};
+use std::{
+    cmp::{max, min},
+    collections::BTreeMap,
+};
 
 use bracket_lib::prelude::*;
-use specs::WorldExt;
+use specs::prelude::*;
 use thiserror::Error;
 
-use crate::{map::Map, spawner, DebugOptions, RunState, State};
+use crate::{
+    components::{Door, Hidden, Name, Position},
+    map::Map,
+    map_text::{MapTextError, TextMap},
+    raws,
+    rng::GameRng,
+    spawner, DebugOptions, RunState, State,
+};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Line {
@@ -62,6 +72,10 @@ pub enum ConsoleError {
     },
     #[error("insufficient arguments, expected at least {0}")]
     InsufficientArguments(usize),
+    #[error("{0}")]
+    MapText(#[from] MapTextError),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
 }
 
 pub fn console_input(gs: &mut State, ctx: &mut BTerm) -> RunState {
@@ -180,6 +194,12 @@ fn execute(gs: &mut State, input: &str) -> Result<RunState, ConsoleError> {
             "spawn" => {
                 spawn_commands(gs, &parts[1..])?;
             }
+            "seed" => {
+                return seed_commands(gs, &parts[1..]);
+            }
+            "map" => {
+                return map_commands(gs, &parts[1..]);
+            }
             "fog" => {
                 let mut debug = gs.ecs.fetch_mut::<DebugOptions>();
                 debug.fog_off = !debug.fog_off;
@@ -188,6 +208,13 @@ fn execute(gs: &mut State, input: &str) -> Result<RunState, ConsoleError> {
                 let mut debug = gs.ecs.fetch_mut::<DebugOptions>();
                 debug.reveal_hidden = !debug.reveal_hidden;
             }
+            "mapgen" => {
+                let mut debug = gs.ecs.fetch_mut::<DebugOptions>();
+                debug.show_mapgen = !debug.show_mapgen;
+            }
+            "reload" => {
+                reload_raws(&gs.ecs);
+            }
             "descend" => {
                 let mut console = gs.ecs.fetch_mut::<Console>();
                 console.history.push(Output("Descending...".into()));
@@ -210,38 +237,38 @@ fn execute(gs: &mut State, input: &str) -> Result<RunState, ConsoleError> {
                 console.history.push(Output(
                     "reveal                   - toggle reveal hidden".into(),
                 ));
-                console
-                    .history
-                    .push(Output("spawn potion             - spawn potion".into()));
                 console.history.push(Output(
-                    "spawn magicmissile       - spawn magic missile scroll".into(),
+                    "mapgen                   - toggle map generation replay".into(),
                 ));
                 console.history.push(Output(
-                    "spawn fireball           - spawn fireball scroll".into(),
+                    "spawn                    - list what can be spawned".into(),
                 ));
                 console.history.push(Output(
-                    "spawn confusion          - spawn confusion scroll".into(),
+                    "spawn <name>             - spawn by name, e.g. spawn tower shield".into(),
+                ));
+                console.history.push(Output(
+                    "reload                   - reload the raws and mods".into(),
                 ));
                 console
                     .history
-                    .push(Output("spawn dagger             - spawn dagger".into()));
-                console
-                    .history
-                    .push(Output("spawn shield             - spawn shield".into()));
-                console
-                    .history
-                    .push(Output("spawn longsword          - spawn longsword".into()));
+                    .push(Output("descend                  - go down 1 level".into()));
                 console.history.push(Output(
-                    "spawn towershield        - spawn towershield".into(),
+                    "console height <lines>   - set console height".into(),
                 ));
                 console
                     .history
-                    .push(Output("spawn trap               - spawn trap".into()));
+                    .push(Output("seed                     - show world seed".into()));
+                console.history.push(Output(
+                    "seed <n>                 - new game with seed".into(),
+                ));
                 console
                     .history
-                    .push(Output("descend                  - go down 1 level".into()));
+                    .push(Output("seed random              - unfix the seed".into()));
                 console.history.push(Output(
-                    "console height <lines>   - set console height".into(),
+                    "map dump <name>          - write level to maps/<name>.txt".into(),
+                ));
+                console.history.push(Output(
+                    "map load <name>          - replace level with maps/<name>.txt".into(),
                 ));
             }
             c => return Err(ConsoleError::UnknownCommand(c.into())),
@@ -250,44 +277,207 @@ fn execute(gs: &mut State, input: &str) -> Result<RunState, ConsoleError> {
     Ok(RunState::Console)
 }
 
+/// Reloads the raws, reporting whether that worked in the console
+pub fn reload_raws(ecs: &World) {
+    let line = match raws::load_raws() {
+        Ok(()) => {
+            let mods = raws::RAWS.lock().unwrap().mods().len();
+            format!("Reloaded the raws, with {} mods", mods)
+        }
+        Err(e) => {
+            console::log(format!("error loading raws: {}", e));
+            format!("Kept the old raws, the new ones have an error: {}", e)
+        }
+    };
+    ecs.fetch_mut::<Console>().history.push(Line::Output(line));
+}
+
 fn spawn_commands(gs: &mut State, args: &[&str]) -> Result<(), ConsoleError> {
-    let player_pos = {
-        let pos = gs.ecs.fetch::<Point>();
-        *pos.clone()
+    use Line::*;
+
+    let name = args.join(" ");
+    if name.is_empty() {
+        let keys = raws::RAWS.lock().unwrap().keys().join(", ");
+        let mut console = gs.ecs.fetch_mut::<Console>();
+        console.history.push(Output(format!("Can spawn: {}", keys)));
+        return Ok(());
+    }
+
+    let key = raws::RAWS
+        .lock()
+        .unwrap()
+        .find_key(&name)
+        .map(|key| key.to_string())
+        .ok_or_else(|| ConsoleError::UnknownCommand(format!("spawn {}", name)))?;
+    let player_pos = *gs.ecs.fetch::<Point>();
+    let position = Position {
+        x: player_pos.x,
+        y: player_pos.y,
     };
-    if let Some(&arg) = args.get(0) {
-        match arg {
-            "potion" => {
-                spawner::health_potion(&mut gs.ecs, player_pos.x, player_pos.y);
-            }
-            "magicmissile" => {
-                spawner::magic_missile_scroll(&mut gs.ecs, player_pos.x, player_pos.y);
-            }
-            "fireball" => {
-                spawner::fireball_scroll(&mut gs.ecs, player_pos.x, player_pos.y);
-            }
-            "confusion" => {
-                spawner::confusion_scroll(&mut gs.ecs, player_pos.x, player_pos.y);
-            }
-            "dagger" => {
-                spawner::dagger(&mut gs.ecs, player_pos.x, player_pos.y);
-            }
-            "shield" => {
-                spawner::shield(&mut gs.ecs, player_pos.x, player_pos.y);
-            }
-            "longsword" => {
-                spawner::longsword(&mut gs.ecs, player_pos.x, player_pos.y);
-            }
-            "towershield" => {
-                spawner::tower_shield(&mut gs.ecs, player_pos.x, player_pos.y);
+    if spawner::spawn_named(&mut gs.ecs, &key, position).is_none() {
+        let mut console = gs.ecs.fetch_mut::<Console>();
+        console.history.push(Output(format!(
+            "{} is unique, and has already been spawned",
+            key
+        )));
+    }
+    Ok(())
+}
+
+fn seed_commands(gs: &mut State, args: &[&str]) -> Result<RunState, ConsoleError> {
+    use Line::*;
+
+    match args.get(0) {
+        None => {
+            let seed = gs.ecs.fetch::<GameRng>().seed();
+            let fixed = gs.ecs.fetch::<DebugOptions>().fixed_seed.is_some();
+            let mut console = gs.ecs.fetch_mut::<Console>();
+            console.history.push(Output(format!(
+                "World seed: {}{}",
+                seed,
+                if fixed { " (fixed)" } else { "" }
+            )));
+        }
+        Some(&"random") => {
+            gs.ecs.fetch_mut::<DebugOptions>().fixed_seed = None;
+            let mut console = gs.ecs.fetch_mut::<Console>();
+            console
+                .history
+                .push(Output("New games will use a random seed".into()));
+        }
+        Some(&arg) => {
+            let seed = arg
+                .parse::<u64>()
+                .map_err(|e| ConsoleError::InvalidArgument {
+                    source: anyhow::anyhow!(e),
+                    expected: "an unsigned integer".into(),
+                    found: arg.into(),
+                })?;
+            gs.ecs.fetch_mut::<DebugOptions>().fixed_seed = Some(seed);
+            gs.new_game();
+            let mut console = gs.ecs.fetch_mut::<Console>();
+            console
+                .history
+                .push(Output(format!("Starting new game with seed {}", seed)));
+            gs.mapgen_next_state = Some(RunState::PreRun);
+            return Ok(RunState::MapGeneration);
+        }
+    }
+    Ok(RunState::Console)
+}
+
+fn map_commands(gs: &mut State, args: &[&str]) -> Result<RunState, ConsoleError> {
+    use Line::*;
+
+    let command = *args.get(0).ok_or(ConsoleError::InsufficientArguments(2))?;
+    let name = *args.get(1).ok_or(ConsoleError::InsufficientArguments(2))?;
+    let path = format!("./maps/{}.txt", name);
+    match command {
+        "dump" => {
+            let (level, left_out) = current_level_as_text(gs);
+            write_map_file(&path, &level.to_text())?;
+            let mut console = gs.ecs.fetch_mut::<Console>();
+            for name in left_out {
+                console.history.push(Output(format!(
+                    "Left out the {}, it has no map glyph",
+                    name
+                )));
             }
-            "trap" => {
-                spawner::bear_trap(&mut gs.ecs, player_pos.x, player_pos.y);
+            console
+                .history
+                .push(Output(format!("Wrote level to {}", path)));
+        }
+        "load" => {
+            let level = TextMap::parse(&read_map_file(&path)?)?;
+            gs.transition_to_text_map(level);
+            let mut console = gs.ecs.fetch_mut::<Console>();
+            console
+                .history
+                .push(Output(format!("Loaded level from {}", path)));
+            return Ok(RunState::PreRun);
+        }
+        c => return Err(ConsoleError::UnknownCommand(format!("map {}", c))),
+    }
+    Ok(RunState::Console)
+}
+
+/// The current level as a text map, along with the names of anything on it
+/// that can't be written out, because the raws give it no map glyph
+fn current_level_as_text(gs: &State) -> (TextMap, Vec<String>) {
+    let raws = raws::RAWS.lock().unwrap();
+    let map = gs.ecs.fetch::<Map>();
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let player_pos = gs.ecs.fetch::<Point>();
+    let entities = gs.ecs.entities();
+    let positions = gs.ecs.read_storage::<Position>();
+    let names = gs.ecs.read_storage::<Name>();
+    let doors = gs.ecs.read_storage::<Door>();
+    let hidden = gs.ecs.read_storage::<Hidden>();
+
+    let mut spawns = Vec::new();
+    let mut open_doors = Vec::new();
+    let mut left_out = Vec::new();
+    for (entity, pos, name) in (&entities, &positions, &names).join() {
+        if entity == *player_entity {
+            continue;
+        }
+        if raws.get(&name.name).and_then(|t| t.map_glyph()).is_none() {
+            left_out.push(name.name.clone());
+            continue;
+        }
+        let idx = map.xy_idx(pos.x, pos.y);
+        match doors.get(entity) {
+            // A secret door that has been found is just a door now
+            Some(door) if hidden.get(entity).is_none() => {
+                spawns.push((idx, "Door".to_string()));
+                if door.open {
+                    open_doors.push(idx);
+                }
             }
-            c => return Err(ConsoleError::UnknownCommand(format!("spawn {}", c).into())),
+            _ => spawns.push((idx, name.name.clone())),
         }
     }
-    Ok(())
+    let mut metadata = BTreeMap::new();
+    metadata.insert(
+        "seed".to_string(),
+        gs.ecs.fetch::<GameRng>().seed().to_string(),
+    );
+
+    let level = TextMap {
+        map: (*map).clone(),
+        start: Some(*player_pos),
+        spawns,
+        open_doors,
+        metadata,
+    };
+    (level, left_out)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_map_file(path: &str, text: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all("./maps")?;
+    std::fs::write(path, text)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_map_file(path: &str) -> std::io::Result<String> {
+    std::fs::read_to_string(path)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_map_file(_path: &str, _text: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "map files aren't available in the browser",
+    ))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_map_file(_path: &str) -> std::io::Result<String> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "map files aren't available in the browser",
+    ))
 }
 
 fn console_commands(gs: &mut State, args: &[&str]) -> Result<(), ConsoleError> {
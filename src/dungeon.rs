@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bracket_lib::random::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+use crate::{
+    components::{OtherLevelPosition, Position, Viewshed},
+    identification::{Disguise, ItemKind},
+    map::Map,
+};
+
+/// Everything about a run that outlasts a level: the levels visited other
+/// than the current one, the uniques that have turned up, and what the items
+/// that need identifying look like
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+pub struct MasterDungeonMap {
+    maps: BTreeMap<i32, Map>,
+    #[serde(default)]
+    uniques: BTreeSet<String>,
+    #[serde(default)]
+    disguises: BTreeMap<String, Disguise>,
+    #[serde(default)]
+    identified: BTreeSet<String>,
+}
+
+impl MasterDungeonMap {
+    pub fn new() -> MasterDungeonMap {
+        MasterDungeonMap {
+            maps: BTreeMap::new(),
+            uniques: BTreeSet::new(),
+            disguises: BTreeMap::new(),
+            identified: BTreeSet::new(),
+        }
+    }
+
+    pub fn has_spawned(&self, unique: &str) -> bool {
+        self.uniques.contains(unique)
+    }
+
+    /// Makes sure the unique is never spawned again this run
+    pub fn mark_spawned(&mut self, unique: &str) {
+        self.uniques.insert(unique.to_string());
+    }
+
+    /// What the item `key` looks like until it's identified, picked the first
+    /// time one spawns
+    pub fn disguise(
+        &mut self,
+        key: &str,
+        kind: ItemKind,
+        rng: &mut RandomNumberGenerator,
+    ) -> Disguise {
+        if let Some(disguise) = self.disguises.get(key) {
+            return disguise.clone();
+        }
+        let taken: Vec<&str> = (self.disguises.values())
+            .map(|disguise| disguise.name.as_str())
+            .collect();
+        let disguise = Disguise::new(kind, &taken, rng);
+        self.disguises.insert(key.to_string(), disguise.clone());
+        disguise
+    }
+
+    /// Returns whether there was anything to learn
+    pub fn identify(&mut self, key: &str) -> bool {
+        self.disguises.contains_key(key) && self.identified.insert(key.to_string())
+    }
+
+    /// What the player knows the item `key` as
+    pub fn item_name(&self, key: &str) -> String {
+        match self.disguises.get(key) {
+            Some(disguise) if !self.identified.contains(key) => disguise.name.clone(),
+            _ => key.to_string(),
+        }
+    }
+
+    pub fn store_map(&mut self, map: &Map) {
+        self.maps.insert(map.depth, map.clone());
+    }
+
+    pub fn get_map(&self, depth: i32) -> Option<Map> {
+        self.maps.get(&depth).map(|map| {
+            let mut map = map.clone();
+            // The content index isn't saved, so it may be missing
+            map.tile_content = vec![Vec::new(); map.tiles.len()];
+            map
+        })
+    }
+}
+
+/// Moves everything on the current level, except the player, off the map
+pub fn freeze_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let player_entity = ecs.fetch::<Entity>();
+    let map_depth = ecs.fetch::<Map>().depth;
+
+    let mut pos_to_delete: Vec<Entity> = Vec::new();
+    for (entity, pos) in (&entities, &positions).join() {
+        if entity != *player_entity {
+            other_level_positions
+                .insert(
+                    entity,
+                    OtherLevelPosition {
+                        x: pos.x,
+                        y: pos.y,
+                        depth: map_depth,
+                    },
+                )
+                .expect("Insert fail");
+            pos_to_delete.push(entity);
+        }
+    }
+
+    for p in pos_to_delete.iter() {
+        positions.remove(*p);
+    }
+}
+
+/// Puts everything that was frozen on the current level back on the map
+pub fn thaw_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let map_depth = ecs.fetch::<Map>().depth;
+
+    let mut pos_to_delete: Vec<Entity> = Vec::new();
+    for (entity, pos) in (&entities, &other_level_positions).join() {
+        if pos.depth == map_depth {
+            positions
+                .insert(entity, Position { x: pos.x, y: pos.y })
+                .expect("Insert fail");
+            if let Some(viewshed) = viewsheds.get_mut(entity) {
+                viewshed.dirty = true;
+            }
+            pos_to_delete.push(entity);
+        }
+    }
+
+    for p in pos_to_delete.iter() {
+        other_level_positions.remove(*p);
+    }
+}
@@ -1,18 +1,22 @@
 use bracket_lib::prelude::*;
 use specs::prelude::*;
 
-use crate::{components::Viewshed, gamelog::GameLog, DebugOptions};
 use crate::{
-    components::{CombatStats, HungerClock, HungerState, Name, Player, Position},
-    RunState, State,
+    camera,
+    components::{Hidden, InBackpack},
+    console,
+    dungeon::MasterDungeonMap,
+    shop,
 };
+use crate::{components::Viewshed, gamelog::GameLog, DebugOptions};
 use crate::{
-    components::{Equipped, GivenName},
+    components::{counted, named, Burden, Cursed, Equipped, GivenName, ItemValue, Stack, Wallet},
     map::{Map, TileType},
+    raws::RAWS,
 };
 use crate::{
-    components::{Hidden, InBackpack},
-    console,
+    components::{CombatStats, HungerClock, HungerState, Lantern, Name, Player, Position},
+    RunState, State,
 };
 
 #[derive(PartialEq, Copy, Clone)]
@@ -58,6 +62,14 @@ pub fn main_menu(gs: &mut State, ctx: &mut BTerm) -> MainMenuResult {
             ctx.print_color_centered(26, RGB::named(WHITE), RGB::named(BLACK), "Quit");
         }
 
+        let raws = RAWS.lock().unwrap();
+        if !raws.mods().is_empty() {
+            ctx.print_color_centered(30, RGB::named(GREY), RGB::named(BLACK), "Mods");
+            for (i, label) in raws.mods().iter().enumerate() {
+                ctx.print_color_centered(31 + i as i32, RGB::named(GREY), RGB::named(BLACK), label);
+            }
+        }
+
         match ctx.key {
             None => {
                 return MainMenuResult::NoSelection {
@@ -121,7 +133,7 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
     ctx.draw_box(0, 43, 79, 6, RGB::named(WHITE), RGB::named(BLACK));
 
     let map = ecs.fetch::<Map>();
-    let depth = format!("Depth: {}", map.depth);
+    let depth = format!("{}, depth {}", map.theme.name(), map.depth);
     ctx.print_color(2, 43, RGB::named(YELLOW), RGB::named(BLACK), &depth);
 
     let combat_stats = ecs.read_storage::<CombatStats>();
@@ -129,11 +141,11 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
     let hunger = ecs.read_storage::<HungerClock>();
     for (_player, stats, hc) in (&players, &combat_stats, &hunger).join() {
         let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
-        ctx.print_color(12, 43, RGB::named(YELLOW), RGB::named(BLACK), &health);
+        ctx.print_color(20, 43, RGB::named(YELLOW), RGB::named(BLACK), &health);
         ctx.draw_bar_horizontal(
-            28,
+            36,
+            43,
             43,
-            51,
             stats.hp,
             stats.max_hp,
             RGB::named(RED),
@@ -154,6 +166,34 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
         }
     }
 
+    let wallets = ecs.read_storage::<Wallet>();
+    for (_player, wallet) in (&players, &wallets).join() {
+        let gold = format!("Gold: {}", wallet.gold);
+        ctx.print_color(45, 42, RGB::named(YELLOW), RGB::named(BLACK), &gold);
+    }
+
+    let burdens = ecs.read_storage::<Burden>();
+    for (_player, burden) in (&players, &burdens).join() {
+        if burden.is_overburdened() {
+            ctx.print_color(
+                30,
+                42,
+                RGB::named(ORANGE),
+                RGB::named(BLACK),
+                "Overburdened",
+            );
+        }
+    }
+
+    let lanterns = ecs.read_storage::<Lantern>();
+    for (_player, lantern) in (&players, &lanterns).join() {
+        if lantern.fuel <= 0 {
+            ctx.print_color(58, 42, RGB::named(RED), RGB::named(BLACK), "Lantern out");
+        } else if lantern.fuel < Lantern::LOW_FUEL {
+            ctx.print_color(58, 42, RGB::named(ORANGE), RGB::named(BLACK), "Lantern low");
+        }
+    }
+
     let log = ecs.fetch::<GameLog>();
 
     let mut y = 44;
@@ -181,29 +221,48 @@ pub fn draw_tooltips(ecs: &World, ctx: &mut BTerm) {
     let given_names = ecs.read_storage::<GivenName>();
     let positions = ecs.read_storage::<Position>();
     let hidden = ecs.read_storage::<Hidden>();
+    let dungeon = ecs.fetch::<MasterDungeonMap>();
+    let stacks = ecs.read_storage::<Stack>();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
+    let mouse_screen = Point::new(mouse_pos.0, mouse_pos.1);
+    if !camera::is_in_viewport(mouse_screen) {
+        return;
+    }
+    let mouse_world = camera::screen_to_world(ecs, mouse_screen);
+    if mouse_world.x < 0
+        || mouse_world.x >= map.width
+        || mouse_world.y < 0
+        || mouse_world.y >= map.height
+    {
         return;
     }
 
     let mut tooltip: Vec<String> = Vec::new();
     for (entity, name, position, _hidden) in (&entities, &names, &positions, !&hidden).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0
-            && position.y == mouse_pos.1
+        if position.x == mouse_world.x
+            && position.y == mouse_world.y
             && (debug.fog_off || map.visible_tiles[idx])
         {
             if let Some(gn) = given_names.get(entity) {
                 tooltip.push(format!("{}, {}", name.name, gn.name));
             } else {
-                tooltip.push(name.name.to_string());
+                tooltip.push(counted(dungeon.item_name(&name.name), stacks.get(entity)));
             }
         }
     }
 
-    if map.tiles[map.xy_idx(mouse_pos.0, mouse_pos.1)] == TileType::DownStairs {
-        tooltip.push("Staircase".into());
+    match map.tiles[map.xy_idx(mouse_world.x, mouse_world.y)] {
+        TileType::DownStairs => tooltip.push("Staircase down".into()),
+        TileType::UpStairs => tooltip.push("Staircase up".into()),
+        TileType::ShallowWater => tooltip.push("Shallow water".into()),
+        TileType::DeepWater => tooltip.push("Deep water".into()),
+        TileType::Lava => tooltip.push("Lava".into()),
+        TileType::Chasm => tooltip.push("Chasm".into()),
+        TileType::Rubble => tooltip.push("Rubble".into()),
+        TileType::Bridge => tooltip.push("Bridge".into()),
+        _ => {}
     }
 
     if !tooltip.is_empty() {
@@ -314,7 +373,10 @@ pub enum ItemMenuResult {
 pub fn show_inventory(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
+    let dungeon = gs.ecs.fetch::<MasterDungeonMap>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let stacks = gs.ecs.read_storage::<Stack>();
+    let burdens = gs.ecs.read_storage::<Burden>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -338,6 +400,20 @@ pub fn show_inventory(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Optio
         RGB::named(BLACK),
         "Inventory",
     );
+    if let Some(burden) = burdens.get(*player_entity) {
+        let colour = if burden.is_overburdened() {
+            RGB::named(ORANGE)
+        } else {
+            RGB::named(YELLOW)
+        };
+        ctx.print_color(
+            28,
+            y - 2,
+            colour,
+            RGB::named(BLACK),
+            &format!("{:.1}/{:.0} lbs", burden.carried, burden.capacity),
+        );
+    }
     ctx.print_color(
         18,
         y + count as i32 + 1,
@@ -362,7 +438,11 @@ pub fn show_inventory(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Optio
         );
         ctx.set(19, y, RGB::named(WHITE), RGB::named(BLACK), to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(
+            21,
+            y,
+            &counted(dungeon.item_name(&name.name), stacks.get(entity)),
+        );
         equipable.push(entity);
         y += 1;
         j += 1;
@@ -392,7 +472,9 @@ pub fn show_inventory(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Optio
 pub fn drop_item_menu(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
+    let dungeon = gs.ecs.fetch::<MasterDungeonMap>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let stacks = gs.ecs.read_storage::<Stack>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -440,7 +522,11 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Optio
         );
         ctx.set(19, y, RGB::named(WHITE), RGB::named(BLACK), to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(
+            21,
+            y,
+            &counted(dungeon.item_name(&name.name), stacks.get(entity)),
+        );
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -464,10 +550,63 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Optio
     }
 }
 
+/// Asks how many of a stack to drop, with `count` picked so far
+pub fn drop_count_menu(
+    gs: &mut State,
+    ctx: &mut BTerm,
+    item: Entity,
+    count: i32,
+) -> (ItemMenuResult, i32) {
+    let names = gs.ecs.read_storage::<Name>();
+    let dungeon = gs.ecs.fetch::<MasterDungeonMap>();
+    let stacks = gs.ecs.read_storage::<Stack>();
+    let total = stacks.get(item).map_or(1, |stack| stack.count);
+    let name = names
+        .get(item)
+        .map_or_else(String::new, |name| dungeon.item_name(&name.name));
+
+    ctx.draw_box(15, 22, 40, 4, RGB::named(WHITE), RGB::named(BLACK));
+    ctx.print_color(
+        18,
+        22,
+        RGB::named(YELLOW),
+        RGB::named(BLACK),
+        "Drop How Many?",
+    );
+    ctx.print(17, 24, &format!("< {} > of {} {}", count, total, name));
+    ctx.print_color(
+        18,
+        26,
+        RGB::named(YELLOW),
+        RGB::named(BLACK),
+        "ENTER to drop, ESCAPE to cancel",
+    );
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, count),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => (ItemMenuResult::Cancel, count),
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                (ItemMenuResult::Selected, count)
+            }
+            VirtualKeyCode::Left | VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                (ItemMenuResult::NoResponse, i32::max(1, count - 1))
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => {
+                (ItemMenuResult::NoResponse, i32::min(total, count + 1))
+            }
+            VirtualKeyCode::A => (ItemMenuResult::NoResponse, total),
+            _ => (ItemMenuResult::NoResponse, count),
+        },
+    }
+}
+
 pub fn remove_item_menu(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
+    let dungeon = gs.ecs.fetch::<MasterDungeonMap>();
     let backpack = gs.ecs.read_storage::<Equipped>();
+    let cursed = gs.ecs.read_storage::<Cursed>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -515,7 +654,12 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Opt
         );
         ctx.set(19, y, RGB::named(WHITE), RGB::named(BLACK), to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        match cursed.get(entity) {
+            Some(curse) if curse.known => {
+                ctx.print(21, y, &format!("cursed {}", dungeon.item_name(&name.name)))
+            }
+            _ => ctx.print(21, y, &dungeon.item_name(&name.name)),
+        }
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -557,7 +701,8 @@ pub fn ranged_target(
         for idx in visible.visible_tiles.iter() {
             let distance = DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(BLUE));
+                let screen_pos = camera::world_to_screen(&gs.ecs, *idx);
+                ctx.set_bg(screen_pos.x, screen_pos.y, RGB::named(BLUE));
                 available_cells.push(idx);
             }
         }
@@ -566,19 +711,17 @@ pub fn ranged_target(
     }
 
     let mouse_pos = ctx.mouse_pos();
+    let mouse_world = camera::screen_to_world(&gs.ecs, Point::new(mouse_pos.0, mouse_pos.1));
     let mut valid_target = false;
     for idx in available_cells.iter() {
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
+        if idx.x == mouse_world.x && idx.y == mouse_world.y {
             valid_target = true;
         }
     }
     if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(CYAN));
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            return (ItemMenuResult::Selected, Some(mouse_world));
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(RED));
@@ -628,3 +771,132 @@ pub fn game_over(ctx: &mut BTerm) -> GameOverResult {
         _ => GameOverResult::NoSelection,
     }
 }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ShopResult {
+    Cancel,
+    NoResponse,
+    /// Go from buying to selling, or back
+    Switch,
+    Selected,
+}
+
+/// Lists what `vendor` has for sale, or with `selling` what the player has
+/// that the vendor will pay for, with prices and the player's gold
+pub fn shop_menu(
+    gs: &mut State,
+    ctx: &mut BTerm,
+    vendor: Entity,
+    selling: bool,
+) -> (ShopResult, Option<Entity>) {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let given_names = gs.ecs.read_storage::<GivenName>();
+    let dungeon = gs.ecs.fetch::<MasterDungeonMap>();
+    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let values = gs.ecs.read_storage::<ItemValue>();
+    let stacks = gs.ecs.read_storage::<Stack>();
+    let wallets = gs.ecs.read_storage::<Wallet>();
+    let entities = gs.ecs.entities();
+
+    let owner = if selling { *player_entity } else { vendor };
+    let wares: Vec<(Entity, String, i32)> = (&entities, &backpack, &names, &values)
+        .join()
+        .filter(|item| item.1.owner == owner)
+        .map(|(entity, _pack, name, value)| {
+            let label = counted(dungeon.item_name(&name.name), stacks.get(entity));
+            let price = if selling {
+                shop::sell_price(value)
+            } else {
+                shop::buy_price(value)
+            };
+            (entity, label, price)
+        })
+        .filter(|(_, _, price)| *price > 0)
+        .collect();
+    let count = wares.len();
+
+    let mut y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        51,
+        (count + 3) as i32,
+        RGB::named(WHITE),
+        RGB::named(BLACK),
+    );
+    let shopkeeper = named(names.get(vendor), given_names.get(vendor));
+    let title = if selling {
+        format!("Sell to {}", shopkeeper)
+    } else {
+        format!("Buy from {}", shopkeeper)
+    };
+    ctx.print_color(18, y - 2, RGB::named(YELLOW), RGB::named(BLACK), &title);
+    let gold = wallets.get(*player_entity).map_or(0, |wallet| wallet.gold);
+    ctx.print_color(
+        50,
+        y - 2,
+        RGB::named(YELLOW),
+        RGB::named(BLACK),
+        &format!("{} gold", gold),
+    );
+    let footer = if selling {
+        "TAB to buy, ESCAPE to leave"
+    } else {
+        "TAB to sell, ESCAPE to leave"
+    };
+    if selling {
+        let purse = wallets.get(vendor).map_or(0, |wallet| wallet.gold);
+        ctx.print_color(
+            50,
+            y + count as i32 + 1,
+            RGB::named(YELLOW),
+            RGB::named(BLACK),
+            &format!("They have {} gold", purse),
+        );
+    }
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(YELLOW),
+        RGB::named(BLACK),
+        footer,
+    );
+
+    for (j, (_entity, label, price)) in wares.iter().enumerate() {
+        ctx.set(17, y, RGB::named(WHITE), RGB::named(BLACK), to_cp437('('));
+        ctx.set(
+            18,
+            y,
+            RGB::named(YELLOW),
+            RGB::named(BLACK),
+            97 + j as FontCharType,
+        );
+        ctx.set(19, y, RGB::named(WHITE), RGB::named(BLACK), to_cp437(')'));
+
+        ctx.print(21, y, label);
+        ctx.print_color(
+            50,
+            y,
+            RGB::named(YELLOW),
+            RGB::named(BLACK),
+            &format!("{} gold", price),
+        );
+        y += 1;
+    }
+
+    match ctx.key {
+        None => (ShopResult::NoResponse, None),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => (ShopResult::Cancel, None),
+            VirtualKeyCode::Tab => (ShopResult::Switch, None),
+            _ => {
+                let selection = letter_to_option(key);
+                if selection > -1 && selection < count as i32 {
+                    return (ShopResult::Selected, Some(wares[selection as usize].0));
+                }
+                (ShopResult::NoResponse, None)
+            }
+        },
+    }
+}
@@ -0,0 +1,86 @@
+//! Scrolls and potions go by a made up name until the player finds out what
+//! they are, by using one or reading an identify scroll. Which made up name
+//! goes with which item is picked afresh each run, the first time one spawns,
+//! and kept in the `MasterDungeonMap` along with what has been identified.
+
+use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The kinds of item that start out unidentified
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum ItemKind {
+    Scroll,
+    Potion,
+}
+
+/// What an item looks like before it has been identified
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Disguise {
+    pub name: String,
+    pub colour: RGB,
+}
+
+const SYLLABLES: &[&str] = &[
+    "XY", "ZZY", "FOO", "KLA", "TU", "NOR", "VEX", "ELB", "ASH", "MOR", "QUA", "ZIM", "PRA", "DUL",
+    "OTH", "YRR",
+];
+
+const SCROLL_COLOURS: &[(u8, u8, u8)] = &[
+    (240, 230, 200),
+    (200, 200, 255),
+    (255, 220, 150),
+    (220, 255, 220),
+    (255, 200, 220),
+];
+
+const POTION_LOOKS: &[(&str, (u8, u8, u8))] = &[
+    ("murky", (110, 90, 60)),
+    ("bubbling", (0, 200, 100)),
+    ("cloudy", (200, 200, 210)),
+    ("fizzy", (255, 240, 0)),
+    ("glowing", (120, 255, 255)),
+    ("smoky", (130, 130, 130)),
+    ("oily", (90, 60, 120)),
+    ("shimmering", (255, 150, 255)),
+    ("crimson", (200, 20, 40)),
+    ("viscous", (180, 110, 0)),
+];
+
+impl Disguise {
+    /// A disguise for a `kind` of item, with a name none of `taken` goes by.
+    /// Once every potion look is used up they start getting reused.
+    pub fn new(kind: ItemKind, taken: &[&str], rng: &mut RandomNumberGenerator) -> Disguise {
+        match kind {
+            ItemKind::Scroll => {
+                let (r, g, b) = SCROLL_COLOURS[rng.range(0, SCROLL_COLOURS.len())];
+                loop {
+                    let syllables = rng.range(2, 4);
+                    let label: String = (0..syllables)
+                        .map(|_| SYLLABLES[rng.range(0, SYLLABLES.len())])
+                        .collect();
+                    let name = format!("scroll labelled {}", label);
+                    if !taken.contains(&name.as_str()) {
+                        return Disguise {
+                            name,
+                            colour: RGB::from_u8(r, g, b),
+                        };
+                    }
+                }
+            }
+            ItemKind::Potion => {
+                let name = |adjective: &str| format!("{} potion", adjective);
+                let mut looks: Vec<&(&str, (u8, u8, u8))> = (POTION_LOOKS.iter())
+                    .filter(|(adjective, _)| !taken.contains(&name(adjective).as_str()))
+                    .collect();
+                if looks.is_empty() {
+                    looks = POTION_LOOKS.iter().collect();
+                }
+                let (adjective, (r, g, b)) = looks[rng.range(0, looks.len())];
+                Disguise {
+                    name: name(adjective),
+                    colour: RGB::from_u8(*r, *g, *b),
+                }
+            }
+        }
+    }
+}
@@ -6,26 +6,41 @@ use specs::{
 };
 
 mod audio;
+mod camera;
 mod components;
 mod console;
+mod dungeon;
 mod gamelog;
 mod gui;
+mod identification;
 mod map;
+mod map_builders;
+mod map_text;
+#[cfg(not(target_arch = "wasm32"))]
+mod mapgen_cli;
 mod player;
 mod random_table;
+mod raws;
 mod rect;
+mod rng;
 mod saveload;
+mod shop;
 mod spawner;
 mod systems;
+mod theme;
 
 use crate::{
     components::*,
     console::console_input,
-    map::{draw_map, Map},
+    map::{Map, TileType},
     player::player_input,
+    rng::{GameRng, RngStream},
     systems::*,
 };
 
+/// How long each map generation snapshot is shown for
+const MAPGEN_FRAME_MS: f32 = 300.0;
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum RunState {
     AwaitingInput,
@@ -36,7 +51,17 @@ pub enum RunState {
     MonsterTurn,
     ShowInventory,
     ShowDropItem,
+    ShowDropCount {
+        item: Entity,
+        count: i32,
+    },
     ShowRemoveItem,
+    ShowShopBuy {
+        vendor: Entity,
+    },
+    ShowShopSell {
+        vendor: Entity,
+    },
     ShowTargeting {
         range: i32,
         item: Entity,
@@ -52,31 +77,51 @@ pub enum RunState {
     Loading,
     SaveGame,
     NextLevel,
+    PreviousLevel,
     GameOver,
+    MapGeneration,
 }
 
 pub struct State {
     ecs: World,
+    mapgen_next_state: Option<RunState>,
+    mapgen_history: Vec<Map>,
+    mapgen_index: usize,
+    mapgen_timer: f32,
 }
 
 #[derive(Default)]
 pub struct DebugOptions {
     reveal_hidden: bool,
     fog_off: bool,
+    show_mapgen: bool,
+    fixed_seed: Option<u64>,
 }
 
 impl State {
     fn run_systems(&mut self) {
+        // Index first too, so doors opened or closed since the last run block the right tiles
+        let mut mapindex = MapIndexingSystem {};
+        mapindex.run_now(&self.ecs);
+
+        let mut lighting = LightingSystem {};
+        lighting.run_now(&self.ecs);
+
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
 
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
 
+        let mut decals = DecalSystem {};
+        decals.run_now(&self.ecs);
+
+        let mut terrain = TerrainSystem {};
+        terrain.run_now(&self.ecs);
+
         let mut trigger = TriggerSystem {};
         trigger.run_now(&self.ecs);
 
-        let mut mapindex = MapIndexingSystem {};
         mapindex.run_now(&self.ecs);
 
         let mut melee = MeleeCombatSystem {};
@@ -100,9 +145,15 @@ impl State {
         let mut particles = ParticleSpawnSystem {};
         particles.run_now(&self.ecs);
 
+        let mut encumbrance = EncumbranceSystem {};
+        encumbrance.run_now(&self.ecs);
+
         let mut hunger = HungerSystem {};
         hunger.run_now(&self.ecs);
 
+        let mut corpses = CorpseSystem {};
+        corpses.run_now(&self.ecs);
+
         self.ecs.maintain();
     }
 
@@ -111,95 +162,186 @@ impl State {
         music.run_now(&self.ecs);
     }
 
-    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
-        let entities = self.ecs.entities();
-        let player = self.ecs.read_storage::<Player>();
-        let backpack = self.ecs.read_storage::<InBackpack>();
-        let equipped = self.ecs.read_storage::<Equipped>();
-        let player_entity = self.ecs.fetch::<Entity>();
+    /// Leaves the current level for the one `offset` levels below it
+    fn goto_level(&mut self, offset: i32) {
+        dungeon::freeze_level_entities(&mut self.ecs);
 
-        let mut to_delete: Vec<Entity> = Vec::new();
-        for entity in entities.join() {
-            let mut should_delete = true;
+        let current_depth = {
+            let map = self.ecs.fetch::<Map>();
+            self.ecs
+                .fetch_mut::<dungeon::MasterDungeonMap>()
+                .store_map(&map);
+            map.depth
+        };
+        let first_visit = self.generate_world_map(current_depth + offset, offset);
 
-            // Don't delete the player
-            let p = player.get(entity);
-            if let Some(_p) = p {
-                should_delete = false;
+        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+        if offset < 0 {
+            gamelog
+                .entries
+                .push("You climb back up to the previous level.".to_string());
+        } else if first_visit {
+            // Give the player some health
+            gamelog
+                .entries
+                .push("You descend to the next level, and take a moment to heal.".to_string());
+            let player_entity = self.ecs.fetch::<Entity>();
+            let mut player_health_store = self.ecs.write_storage::<CombatStats>();
+            let player_health = player_health_store.get_mut(*player_entity);
+            if let Some(player_health) = player_health {
+                player_health.hp = i32::max(player_health.hp, player_health.max_hp / 2);
             }
+        } else {
+            gamelog
+                .entries
+                .push("You descend to the next level.".to_string());
+        }
+    }
 
-            // Don't delete the player's equipment
-            let bp = backpack.get(entity);
-            if let Some(bp) = bp {
-                if bp.owner == *player_entity {
-                    should_delete = false;
-                }
+    /// Switches to the map for `new_depth`, building it if it hasn't been visited
+    /// before, and moves the player there. Returns whether the level is new.
+    fn generate_world_map(&mut self, new_depth: i32, offset: i32) -> bool {
+        self.mapgen_index = 0;
+        self.mapgen_timer = 0.0;
+        self.mapgen_history.clear();
+
+        let cached_map = self
+            .ecs
+            .fetch::<dungeon::MasterDungeonMap>()
+            .get_map(new_depth);
+        match cached_map {
+            Some(map) => {
+                self.transition_to_existing_map(map, offset);
+                false
             }
-            let eq = equipped.get(entity);
-            if let Some(eq) = eq {
-                if eq.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
-
-            if should_delete {
-                to_delete.push(entity);
+            None => {
+                self.transition_to_new_map(new_depth);
+                true
             }
         }
-
-        to_delete
     }
 
-    fn goto_next_level(&mut self) {
-        // Delete entities that aren't the player or his/her equipment
-        let to_delete = self.entities_to_remove_on_level_change();
-        for target in to_delete {
-            self.ecs
-                .delete_entity(target)
-                .expect("Unable to delete entity");
+    fn transition_to_new_map(&mut self, new_depth: i32) {
+        let builder = {
+            let mut rng = self.ecs.write_resource::<GameRng>();
+            rng.begin_level(new_depth);
+            let rng = rng.stream(RngStream::MapGen);
+            let mut builder = map_builders::random_builder(new_depth, rng);
+            builder.build_map(rng);
+            builder
+        };
+        self.mapgen_history = builder.get_snapshot_history();
+
+        // Every level but the first has a way back up where the player arrives
+        let player_start = builder.get_starting_position();
+        let mut map = builder.get_map();
+        if new_depth > 1 {
+            let start_idx = map.xy_idx(player_start.x, player_start.y);
+            map.tiles[start_idx] = TileType::UpStairs;
         }
+        *self.ecs.write_resource::<Map>() = map;
+
+        // Spawn bad guys
+        builder.spawn_entities(&mut self.ecs);
+        spawner::spawn_uniques(&mut self.ecs, new_depth, &player_start);
+
+        self.place_player(player_start.x, player_start.y);
+    }
 
-        // Build a new map and place the player
-        let map = {
-            let mut map = self.ecs.write_resource::<Map>();
-            *map = Map::new_map_rooms_and_corridors(map.depth + 1);
-            map.clone()
+    fn transition_to_existing_map(&mut self, map: Map, offset: i32) {
+        // Arrive on the stairs the player took to get here. A level without
+        // them, say one loaded from a text map, has the player on the first
+        // floor there is, or failing that anywhere they can stand.
+        let arrival_tile = if offset < 0 {
+            TileType::DownStairs
+        } else {
+            TileType::UpStairs
         };
+        let width = map.width;
+        let arrival_idx = [arrival_tile, TileType::Floor]
+            .iter()
+            .find_map(|wanted| map.tiles.iter().position(|tile| tile == wanted))
+            .or_else(|| map.tiles.iter().position(|tile| tile.is_walkable()))
+            .unwrap_or(0) as i32;
+        *self.ecs.write_resource::<Map>() = map;
 
-        // Spawn bad guys
-        for room in map.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, map.depth);
+        dungeon::thaw_level_entities(&mut self.ecs);
+
+        self.place_player(arrival_idx % width, arrival_idx / width);
+    }
+
+    /// Swaps the current level for one read from a text map
+    fn transition_to_text_map(&mut self, level: map_text::TextMap) {
+        // Everything on the old level goes, with whatever it carries; only the
+        // player and what they carry stay
+        let to_delete: Vec<Entity> = {
+            let player_entity = self.ecs.fetch::<Entity>();
+            let entities = self.ecs.entities();
+            let positions = self.ecs.read_storage::<Position>();
+            let backpack = self.ecs.read_storage::<InBackpack>();
+            let equipped = self.ecs.read_storage::<Equipped>();
+            let on_level: Vec<Entity> = (&entities, &positions)
+                .join()
+                .filter(|(entity, _)| *entity != *player_entity)
+                .map(|(entity, _)| entity)
+                .collect();
+            let carried = (&entities, &backpack)
+                .join()
+                .filter(|(_, pack)| on_level.contains(&pack.owner))
+                .map(|(item, _)| item);
+            let worn = (&entities, &equipped)
+                .join()
+                .filter(|(_, worn)| on_level.contains(&worn.owner))
+                .map(|(item, _)| item);
+            let carried: Vec<Entity> = carried.chain(worn).collect();
+            on_level.into_iter().chain(carried).collect()
+        };
+        self.ecs
+            .delete_entities(&to_delete)
+            .expect("Unable to delete entities");
+
+        let mut map = level.map;
+        map.depth = self.ecs.fetch::<Map>().depth;
+        for (idx, name) in level.spawns.iter() {
+            let position = Position {
+                x: *idx as i32 % map.width,
+                y: *idx as i32 / map.width,
+            };
+            let entity = spawner::spawn_named(&mut self.ecs, name, position);
+            if let Some(entity) = entity {
+                if level.open_doors.contains(idx) {
+                    spawner::open_door(&mut self.ecs, entity);
+                }
+            }
         }
 
-        // Place the player and update resources
-        let (player_x, player_y) = map.rooms[0].center();
+        // Without a start marker, arrive on the stairs up or down, or any floor
+        let start = level.start.unwrap_or_else(|| {
+            let idx = [TileType::UpStairs, TileType::DownStairs, TileType::Floor]
+                .iter()
+                .find_map(|wanted| map.tiles.iter().position(|tile| tile == wanted))
+                .unwrap_or(0);
+            Point::new(idx as i32 % map.width, idx as i32 / map.width)
+        });
+        *self.ecs.write_resource::<Map>() = map;
+        self.place_player(start.x, start.y);
+    }
+
+    fn place_player(&mut self, x: i32, y: i32) {
         let mut player_position = self.ecs.write_resource::<Point>();
-        *player_position = Point::new(player_x, player_y);
+        *player_position = Point::new(x, y);
         let mut position_components = self.ecs.write_storage::<Position>();
         let player_entity = self.ecs.fetch::<Entity>();
-        let player_pos_comp = position_components.get_mut(*player_entity);
-        if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
+        if let Some(player_pos_comp) = position_components.get_mut(*player_entity) {
+            player_pos_comp.x = x;
+            player_pos_comp.y = y;
         }
 
         // Mark the player's visibility as dirty
         let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        let vs = viewshed_components.get_mut(*player_entity);
-        if let Some(vs) = vs {
+        if let Some(vs) = viewshed_components.get_mut(*player_entity) {
             vs.dirty = true;
         }
-
-        // Notify the player and give them some health
-        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
-        gamelog
-            .entries
-            .push("You descend to the next level, and take a moment to heal.".to_string());
-        let mut player_health_store = self.ecs.write_storage::<CombatStats>();
-        let player_health = player_health_store.get_mut(*player_entity);
-        if let Some(player_health) = player_health {
-            player_health.hp = i32::max(player_health.hp, player_health.max_hp / 2);
-        }
     }
 
     fn game_over_cleanup(&mut self) {
@@ -211,43 +353,8 @@ impl State {
         for del in to_delete.iter() {
             self.ecs.delete_entity(*del).expect("Deletion failed");
         }
-
-        // Build a new map and place the player
-        let worldmap;
-        {
-            let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            *worldmap_resource = Map::new_map_rooms_and_corridors(1);
-            worldmap = worldmap_resource.clone();
-        }
-
-        // Spawn bad guys
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, 1);
-        }
-
-        // Place the player and update resources
-        let (player_x, player_y) = worldmap.rooms[0].center();
-        let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
-        let mut player_position = self.ecs.write_resource::<Point>();
-        *player_position = Point::new(player_x, player_y);
-        let mut position_components = self.ecs.write_storage::<Position>();
-        let mut player_entity_writer = self.ecs.write_resource::<Entity>();
-        *player_entity_writer = player_entity;
-        let player_pos_comp = position_components.get_mut(player_entity);
-        if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
-        }
-
-        // Mark the player's visibility as dirty
-        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        let vs = viewshed_components.get_mut(player_entity);
-        if let Some(vs) = vs {
-            vs.dirty = true;
-        }
     }
 
-    // TODO remove duplication in new_game and game_over
     fn new_game(&mut self) {
         // Clean up old stuff, if present
         let entities = {
@@ -269,21 +376,26 @@ impl State {
         }
 
         // Set up new stuff
-        let map = Map::new_map_rooms_and_corridors(1);
-        for room in map.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, map.depth);
-        }
-
-        let (player_x, player_y) = map.rooms[0].center();
-
-        let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
-
-        self.ecs.insert(Point::new(player_x, player_y));
+        let seed = self
+            .ecs
+            .fetch::<DebugOptions>()
+            .fixed_seed
+            .unwrap_or_else(GameRng::random_seed);
+        bracket_lib::terminal::console::log(format!("World seed: {}", seed));
+        let mut rng = GameRng::new(seed);
+        rng.begin_level(1);
+        self.ecs.insert(rng);
+        self.ecs.insert(Map::new(1, 0, 0));
+        self.ecs.insert(dungeon::MasterDungeonMap::new());
+        self.ecs.insert(Point::new(0, 0));
+
+        let player_entity = spawner::player(&mut self.ecs, 0, 0);
         self.ecs.insert(player_entity);
-        self.ecs.insert(map);
         self.ecs.insert(gamelog::GameLog {
             entries: vec!["Welcome to the depths".to_string()],
         });
+
+        self.generate_world_map(1, 0);
     }
 }
 
@@ -293,6 +405,17 @@ impl GameState for State {
         cull_dead_particles(&mut self.ecs, ctx);
         self.run_meta_systems();
 
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let raws_changed = self
+                .ecs
+                .fetch_mut::<raws::RawsWatcher>()
+                .poll(ctx.frame_time_ms);
+            if raws_changed {
+                console::reload_raws(&self.ecs);
+            }
+        }
+
         let mut new_runstate = {
             let runstate = self.ecs.fetch::<RunState>();
             *runstate
@@ -300,39 +423,10 @@ impl GameState for State {
 
         // To draw or not to draw???
         match new_runstate {
-            RunState::MainMenu { .. } => {}
+            RunState::MainMenu { .. } | RunState::MapGeneration => {}
             // TODO loading
             _ => {
-                draw_map(&self.ecs, ctx);
-
-                let positions = self.ecs.read_storage::<Position>();
-                let renderables = self.ecs.read_storage::<Renderable>();
-                let hidden = self.ecs.read_storage::<Hidden>();
-                let debug = self.ecs.fetch::<DebugOptions>();
-                let map = self.ecs.fetch::<Map>();
-
-                // Render monsters and objects
-                if debug.reveal_hidden {
-                    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-                    for (pos, render) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if debug.fog_off || map.visible_tiles[idx] {
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
-                        }
-                    }
-                } else {
-                    let mut data = (&positions, &renderables, !&hidden)
-                        .join()
-                        .collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-                    for (pos, render, _hidden) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if debug.fog_off || map.visible_tiles[idx] {
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
-                        }
-                    }
-                }
+                camera::render_camera(&self.ecs, ctx);
 
                 gui::draw_ui(&self.ecs, ctx);
             }
@@ -387,6 +481,8 @@ impl GameState for State {
                     RunState::MagicMapReveal { row, animation } => {
                         new_runstate = RunState::MagicMapReveal { row, animation }
                     }
+                    // Fell into a chasm
+                    RunState::NextLevel => new_runstate = RunState::NextLevel,
                     _ => new_runstate = RunState::MonsterTurn,
                 }
             }
@@ -481,17 +577,76 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
+                        let stacked = self
+                            .ecs
+                            .read_storage::<Stack>()
+                            .get(item_entity)
+                            .map_or(false, |stack| stack.count > 1);
+                        if stacked {
+                            new_runstate = RunState::ShowDropCount {
+                                item: item_entity,
+                                count: 1,
+                            };
+                        } else {
+                            let mut intent = self.ecs.write_storage::<WantsToDropItem>();
+                            intent
+                                .insert(
+                                    *self.ecs.fetch::<Entity>(),
+                                    WantsToDropItem { item: item_entity },
+                                )
+                                .expect("Unable to insert intent");
+                            new_runstate = RunState::PlayerTurn;
+                        }
+                    }
+                }
+            }
+            RunState::ShowDropCount { item, count } => {
+                let result = gui::drop_count_menu(self, ctx, item, count);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {
+                        new_runstate = RunState::ShowDropCount {
+                            item,
+                            count: result.1,
+                        }
+                    }
+                    gui::ItemMenuResult::Selected => {
+                        // Dropping part of a stack splits it in two
+                        let to_drop =
+                            spawner::split_stack(&mut self.ecs, item, result.1).unwrap_or(item);
                         let mut intent = self.ecs.write_storage::<WantsToDropItem>();
                         intent
                             .insert(
                                 *self.ecs.fetch::<Entity>(),
-                                WantsToDropItem { item: item_entity },
+                                WantsToDropItem { item: to_drop },
                             )
                             .expect("Unable to insert intent");
                         new_runstate = RunState::PlayerTurn;
                     }
                 }
             }
+            RunState::ShowShopBuy { vendor } => {
+                let result = gui::shop_menu(self, ctx, vendor, false);
+                match result.0 {
+                    gui::ShopResult::Cancel => new_runstate = RunState::AwaitingInput,
+                    gui::ShopResult::NoResponse => {}
+                    gui::ShopResult::Switch => new_runstate = RunState::ShowShopSell { vendor },
+                    gui::ShopResult::Selected => {
+                        shop::buy(&mut self.ecs, vendor, result.1.unwrap())
+                    }
+                }
+            }
+            RunState::ShowShopSell { vendor } => {
+                let result = gui::shop_menu(self, ctx, vendor, true);
+                match result.0 {
+                    gui::ShopResult::Cancel => new_runstate = RunState::AwaitingInput,
+                    gui::ShopResult::NoResponse => {}
+                    gui::ShopResult::Switch => new_runstate = RunState::ShowShopBuy { vendor },
+                    gui::ShopResult::Selected => {
+                        shop::sell(&mut self.ecs, vendor, result.1.unwrap())
+                    }
+                }
+            }
             RunState::ShowRemoveItem => {
                 let result = gui::remove_item_menu(self, ctx);
                 match result.0 {
@@ -523,7 +678,8 @@ impl GameState for State {
                         gui::MainMenuSelection::NewGame => {
                             self.new_game();
 
-                            new_runstate = RunState::PreLoading
+                            self.mapgen_next_state = Some(RunState::PreLoading);
+                            new_runstate = RunState::MapGeneration
                         }
                         gui::MainMenuSelection::LoadGame => {
                             match saveload::load_game(&mut self.ecs) {
@@ -560,6 +716,7 @@ impl GameState for State {
                 ] {
                     sound_resource.load_audio(url);
                 }
+                sound_resource.load_raws_sounds();
 
                 new_runstate = RunState::Loading;
             }
@@ -587,8 +744,32 @@ impl GameState for State {
                 new_runstate = RunState::PostRun;
             }
             RunState::NextLevel => {
-                self.goto_next_level();
-                new_runstate = RunState::PreRun;
+                self.goto_level(1);
+                self.mapgen_next_state = Some(RunState::PreRun);
+                new_runstate = RunState::MapGeneration;
+            }
+            RunState::PreviousLevel => {
+                self.goto_level(-1);
+                self.mapgen_next_state = Some(RunState::PreRun);
+                new_runstate = RunState::MapGeneration;
+            }
+            // Replay the map builder's snapshots, if enabled
+            RunState::MapGeneration => {
+                let next_state = self.mapgen_next_state.unwrap_or(RunState::PreRun);
+                if !self.ecs.fetch::<DebugOptions>().show_mapgen || self.mapgen_history.is_empty() {
+                    new_runstate = next_state;
+                } else {
+                    camera::render_debug_map(&self.mapgen_history[self.mapgen_index], ctx);
+
+                    self.mapgen_timer += ctx.frame_time_ms;
+                    if self.mapgen_timer > MAPGEN_FRAME_MS {
+                        self.mapgen_timer = 0.0;
+                        self.mapgen_index += 1;
+                        if self.mapgen_index >= self.mapgen_history.len() {
+                            new_runstate = next_state;
+                        }
+                    }
+                }
             }
         }
 
@@ -602,25 +783,54 @@ impl GameState for State {
 }
 
 fn main() -> BError {
+    if let Err(e) = raws::load_raws() {
+        eprintln!("error loading raws: {}", e);
+        std::process::exit(1);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // Headless map generation, see mapgen_cli
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--generate") {
+            if let Err(e) = mapgen_cli::run(&args) {
+                eprintln!("error: {:#}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    }
+
     let mut context = BTermBuilder::simple80x50()
         .with_title("It is a dungeon")
         .build()?;
     // context.with_post_scanlines(true);
 
-    let mut state = State { ecs: World::new() };
+    let mut state = State {
+        ecs: World::new(),
+        mapgen_next_state: None,
+        mapgen_history: Vec::new(),
+        mapgen_index: 0,
+        mapgen_timer: 0.0,
+    };
 
     // Markers
     state.ecs.register::<SimpleMarker<SerializeMe>>();
 
     // Components
     state.ecs.register::<Position>();
+    state.ecs.register::<OtherLevelPosition>();
     state.ecs.register::<Renderable>();
     state.ecs.register::<Player>();
     state.ecs.register::<Viewshed>();
     state.ecs.register::<Name>();
     state.ecs.register::<GivenName>();
     state.ecs.register::<Monster>();
+    state.ecs.register::<DropsLoot>();
+    state.ecs.register::<Unique>();
     state.ecs.register::<BlocksTile>();
+    state.ecs.register::<BlocksVisibility>();
+    state.ecs.register::<Door>();
     state.ecs.register::<CombatStats>();
     state.ecs.register::<WantsToMelee>();
     state.ecs.register::<SufferDamage>();
@@ -643,7 +853,21 @@ fn main() -> BError {
     state.ecs.register::<ParticleLifetime>();
     state.ecs.register::<HungerClock>();
     state.ecs.register::<ProvidesFood>();
+    state.ecs.register::<Corpse>();
     state.ecs.register::<MagicMapper>();
+    state.ecs.register::<Identifier>();
+    state.ecs.register::<Cursed>();
+    state.ecs.register::<CurseRemover>();
+    state.ecs.register::<Stack>();
+    state.ecs.register::<ItemValue>();
+    state.ecs.register::<Gold>();
+    state.ecs.register::<Wallet>();
+    state.ecs.register::<Vendor>();
+    state.ecs.register::<Weight>();
+    state.ecs.register::<Burden>();
+    state.ecs.register::<LightSource>();
+    state.ecs.register::<Lantern>();
+    state.ecs.register::<ProvidesFuel>();
     state.ecs.register::<Animation>();
     state.ecs.register::<Hidden>();
     state.ecs.register::<EntryTrigger>();
@@ -655,7 +879,6 @@ fn main() -> BError {
     state
         .ecs
         .insert(SimpleMarkerAllocator::<SerializeMe>::new());
-    state.ecs.insert(RandomNumberGenerator::new());
     state.ecs.insert(RunState::MainMenu {
         menu_selection: gui::MainMenuSelection::NewGame,
     });
@@ -667,9 +890,35 @@ fn main() -> BError {
         stop: false,
     });
     state.ecs.insert(ParticleBuilder::default());
-    state.ecs.insert(DebugOptions::default());
-
-    state.new_game();
+    state.ecs.insert(DebugOptions {
+        fixed_seed: seed_from_args(),
+        ..Default::default()
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    state.ecs.insert(raws::RawsWatcher::new());
+    // Stand-ins until the menu starts a new game or loads one
+    state.ecs.insert(GameRng::new(0));
+    state.ecs.insert(Map::new(1, 0, 0));
+    state.ecs.insert(dungeon::MasterDungeonMap::new());
+    state.ecs.insert(Point::new(0, 0));
+    state.ecs.insert(gamelog::GameLog {
+        entries: Vec::new(),
+    });
 
     main_loop(context, state)
 }
+
+/// Reads a fixed world seed from `--seed <n>`, for reproducing specific dungeons
+#[cfg(not(target_arch = "wasm32"))]
+fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|seed| seed.parse().ok())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn seed_from_args() -> Option<u64> {
+    None
+}
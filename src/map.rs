@@ -1,45 +1,169 @@
-use std::{
-    cmp::{max, min},
-    collections::HashSet,
-};
+use std::collections::{HashMap, HashSet};
 
 use bracket_lib::prelude::*;
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
-use crate::{gamelog::GameLog, rect::Rect, DebugOptions};
+use crate::{gamelog::GameLog, theme::Theme};
 
-pub const MAPWIDTH: usize = 80;
-pub const MAPHEIGHT: usize = 43;
-pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
+/// Tiles with less light than this are too dark to see
+const LIT_THRESHOLD: f32 = 0.1;
+
+/// How bright a tile that is seen, but not lit, is drawn
+const MIN_BRIGHTNESS: f32 = 0.35;
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
 pub enum TileType {
     Wall,
     Floor,
     DownStairs,
+    UpStairs,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Chasm,
+    Rubble,
+    Bridge,
+}
+
+impl TileType {
+    pub fn is_walkable(self) -> bool {
+        self != TileType::Wall
+    }
+
+    /// How expensive the tile is to path through, so monsters prefer easy ground
+    pub fn movement_cost(self) -> f32 {
+        match self {
+            TileType::ShallowWater => 1.5,
+            TileType::Rubble => 2.0,
+            TileType::DeepWater => 4.0,
+            TileType::Lava => 10.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Whether anything can be left lying on the tile, liquids wash it away
+    pub fn holds_decals(self) -> bool {
+        match self {
+            TileType::Wall
+            | TileType::ShallowWater
+            | TileType::DeepWater
+            | TileType::Lava
+            | TileType::Chasm => false,
+            _ => true,
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum DecalKind {
+    Blood,
+    Scorch,
+    Slime,
+    Water,
+    Footprints,
+}
+
+impl DecalKind {
+    /// How many rounds it takes to fade away completely
+    fn lifetime(self) -> i32 {
+        match self {
+            DecalKind::Blood => 400,
+            DecalKind::Scorch => 800,
+            DecalKind::Slime => 300,
+            DecalKind::Water => 30,
+            DecalKind::Footprints => 60,
+        }
+    }
+
+    fn colour(self) -> RGB {
+        match self {
+            DecalKind::Blood => RGB::from_f32(0.75, 0., 0.),
+            DecalKind::Scorch => RGB::from_f32(0.3, 0.25, 0.2),
+            DecalKind::Slime => RGB::from_f32(0.3, 0.6, 0.1),
+            DecalKind::Water => RGB::from_f32(0.1, 0.25, 0.6),
+            DecalKind::Footprints => RGB::from_f32(0.6, 0.2, 0.1),
+        }
+    }
+
+    /// Drawn instead of the floor glyph, the rest only tint the background
+    fn glyph(self) -> Option<FontCharType> {
+        match self {
+            DecalKind::Scorch => Some(to_cp437(',')),
+            DecalKind::Footprints => Some(to_cp437('"')),
+            _ => None,
+        }
+    }
+
+    /// Anyone stepping onto a slippery decal slides on one more tile
+    pub fn is_slippery(self) -> bool {
+        self == DecalKind::Slime
+    }
+
+    /// Footprints and puddles are left in passing, and don't cover up anything
+    fn is_transient(self) -> bool {
+        self == DecalKind::Water || self == DecalKind::Footprints
+    }
+
+    /// Whether walking through it leaves tracks on the next tile
+    pub fn leaves_tracks(self) -> bool {
+        self == DecalKind::Blood || self == DecalKind::Slime
+    }
+}
+
+/// Something left on the floor, drawn on top of the tile until it fades
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Decal {
+    pub kind: DecalKind,
+    pub turns_left: i32,
+}
+
+impl Decal {
+    fn new(kind: DecalKind) -> Decal {
+        Decal {
+            kind,
+            turns_left: kind.lifetime(),
+        }
+    }
+
+    /// The colour it's drawn with, darkening as it fades
+    fn colour(&self) -> RGB {
+        let strength = 0.3 + 0.7 * self.turns_left as f32 / self.kind.lifetime() as f32;
+        let colour = self.kind.colour();
+        RGB::from_f32(
+            colour.r * strength,
+            colour.g * strength,
+            colour.b * strength,
+        )
+    }
 }
 
 #[derive(Default, Clone, Serialize, Deserialize, Debug)]
 pub struct Map {
     pub tiles: Vec<TileType>,
-    pub rooms: Vec<Rect>,
     pub width: i32,
     pub height: i32,
     pub revealed_tiles: Vec<bool>,
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
     pub depth: i32,
-    pub bloodstains: HashSet<usize>,
+    pub theme: Theme,
+    pub decals: HashMap<usize, Decal>,
+    pub view_blocked: HashSet<usize>,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
     pub tile_content: Vec<Vec<Entity>>,
+
+    /// How much light falls on each tile, worked out again every turn
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub light: Vec<RGB>,
 }
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize] == TileType::Wall
+        self.tiles[idx as usize] == TileType::Wall || self.view_blocked.contains(&idx)
     }
 
     fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
@@ -50,30 +174,30 @@ impl BaseMap for Map {
 
         // Cardinals
         if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
+            exits.push((idx - 1, self.exit_cost(idx - 1, 1.0)))
         };
         if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
+            exits.push((idx + 1, self.exit_cost(idx + 1, 1.0)))
         };
         if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
+            exits.push((idx - w, self.exit_cost(idx - w, 1.0)))
         };
         if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
+            exits.push((idx + w, self.exit_cost(idx + w, 1.0)))
         };
 
         // Diagonals
         if self.is_exit_valid(x - 1, y - 1) {
-            exits.push(((idx - w) - 1, 1.45));
+            exits.push(((idx - w) - 1, self.exit_cost((idx - w) - 1, 1.45)));
         }
         if self.is_exit_valid(x + 1, y - 1) {
-            exits.push(((idx - w) + 1, 1.45));
+            exits.push(((idx - w) + 1, self.exit_cost((idx - w) + 1, 1.45)));
         }
         if self.is_exit_valid(x - 1, y + 1) {
-            exits.push(((idx + w) - 1, 1.45));
+            exits.push(((idx + w) - 1, self.exit_cost((idx + w) - 1, 1.45)));
         }
         if self.is_exit_valid(x + 1, y + 1) {
-            exits.push(((idx + w) + 1, 1.45));
+            exits.push(((idx + w) + 1, self.exit_cost((idx + w) + 1, 1.45)));
         }
 
         exits
@@ -95,6 +219,25 @@ impl Algorithm2D for Map {
 }
 
 impl Map {
+    /// An empty map, consisting solely of solid walls
+    pub fn new(new_depth: i32, width: i32, height: i32) -> Map {
+        let map_tile_count = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::Wall; map_tile_count],
+            width,
+            height,
+            revealed_tiles: vec![false; map_tile_count],
+            visible_tiles: vec![false; map_tile_count],
+            blocked: vec![false; map_tile_count],
+            tile_content: vec![Vec::new(); map_tile_count],
+            light: vec![RGB::from_f32(0., 0., 0.); map_tile_count],
+            depth: new_depth,
+            theme: Theme::for_depth(new_depth),
+            decals: HashMap::new(),
+            view_blocked: HashSet::new(),
+        }
+    }
+
     pub fn xy_idx(&self, x: i32, y: i32) -> usize {
         (y as usize * self.width as usize) + x as usize
     }
@@ -104,106 +247,65 @@ impl Map {
             return false;
         }
         let idx = self.xy_idx(x, y);
-        !self.blocked[idx]
+        // Nobody paths into a chasm, you only end up there on purpose
+        !self.blocked[idx] && self.tiles[idx] != TileType::Chasm
     }
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
-        for y in (room.y1 + 1)..=room.y2 {
-            for x in (room.x1 + 1)..=room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::Floor;
-            }
-        }
+    fn exit_cost(&self, idx: usize, distance: f32) -> f32 {
+        distance * self.tiles[idx].movement_cost()
     }
 
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < MAPCOUNT {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
+    pub fn populate_blocked(&mut self) {
+        for (i, tile) in self.tiles.iter_mut().enumerate() {
+            self.blocked[i] = !tile.is_walkable();
         }
     }
 
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < MAPCOUNT {
-                self.tiles[idx as usize] = TileType::Floor;
+    /// Leaves a fresh decal on the tile, replacing whatever was there unless
+    /// the new one is only passing through
+    pub fn add_decal(&mut self, idx: usize, kind: DecalKind) {
+        if !self.tiles[idx].holds_decals() {
+            return;
+        }
+        if kind.is_transient() {
+            if let Some(existing) = self.decals.get(&idx) {
+                if !existing.kind.is_transient() {
+                    return;
+                }
             }
         }
+        self.decals.insert(idx, Decal::new(kind));
     }
 
-    pub fn populate_blocked(&mut self) {
-        for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
-        }
+    pub fn decal_kind(&self, idx: usize) -> Option<DecalKind> {
+        self.decals.get(&idx).map(|decal| decal.kind)
     }
 
-    pub fn clear_content_index(&mut self) {
-        for content in self.tile_content.iter_mut() {
-            content.clear();
-        }
+    /// Fades every decal by a round, removing the ones that are gone
+    pub fn age_decals(&mut self) {
+        self.decals.retain(|_, decal| {
+            decal.turns_left -= 1;
+            decal.turns_left > 0
+        });
     }
 
-    pub fn new_map_rooms_and_corridors(new_depth: i32) -> Self {
-        let mut map = Map {
-            tiles: vec![TileType::Wall; MAPCOUNT],
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT],
-            depth: new_depth,
-            ..Default::default()
-        };
-
-        const MAX_ROOMS: i32 = 30;
-        const MIN_SIZE: i32 = 6;
-        const MAX_SIZE: i32 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _ in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, MAPWIDTH as i32 - w - 1) - 1;
-            let y = rng.roll_dice(1, MAPHEIGHT as i32 - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-
-            let mut ok = true;
-
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) {
-                    ok = false
-                }
-            }
+    pub fn light_at(&self, idx: usize) -> RGB {
+        self.light
+            .get(idx)
+            .copied()
+            .unwrap_or_else(|| RGB::from_f32(0., 0., 0.))
+    }
 
-            if ok {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                    if rng.range(0, 2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
+    /// Whether there's enough light on the tile to make out what's there
+    pub fn is_lit(&self, idx: usize) -> bool {
+        let light = self.light_at(idx);
+        f32::max(light.r, f32::max(light.g, light.b)) > LIT_THRESHOLD
+    }
 
-                map.rooms.push(new_room);
-            }
+    pub fn clear_content_index(&mut self) {
+        for content in self.tile_content.iter_mut() {
+            content.clear();
         }
-
-        let stairs_position = map.rooms[map.rooms.len() - 1].center();
-        let stairs_idx = map.xy_idx(stairs_position.0, stairs_position.1);
-        map.tiles[stairs_idx] = TileType::DownStairs;
-
-        map
     }
 }
 
@@ -232,69 +334,93 @@ fn wall_glyph(map: &Map, x: i32, y: i32) -> FontCharType {
         mask += 8;
     }
 
-    match mask {
-        0 => 9,    // Pillar because we can't see neighbors
-        1 => 186,  // Wall only to the north
-        2 => 186,  // Wall only to the south
-        3 => 186,  // Wall to the north and south
-        4 => 205,  // Wall only to the west
-        5 => 188,  // Wall to the north and west
-        6 => 187,  // Wall to the south and west
-        7 => 185,  // Wall to the north, south and west
-        8 => 205,  // Wall only to the east
-        9 => 200,  // Wall to the north and east
-        10 => 201, // Wall to the south and east
-        11 => 204, // Wall to the north, south and east
-        12 => 205, // Wall to the east and west
-        13 => 202, // Wall to the east, west, and south
-        14 => 203, // Wall to the east, west, and north
-        15 => 206, // ╬ Wall on all sides
-        _ => 35,   // We missed one?
-    }
+    map.theme.wall_glyph(mask)
 }
 
-pub fn draw_map(ecs: &World, ctx: &mut BTerm) {
-    let debug = ecs.fetch::<DebugOptions>();
-    let map = ecs.fetch::<Map>();
+/// Tints a colour by the light falling on it
+fn shade(colour: RGB, light: RGB) -> RGB {
+    let brightness = |channel: f32| MIN_BRIGHTNESS + (1. - MIN_BRIGHTNESS) * channel;
+    RGB::from_f32(
+        colour.r * brightness(light.r),
+        colour.g * brightness(light.g),
+        colour.b * brightness(light.b),
+    )
+}
 
-    let mut y = 0;
-    let mut x = 0;
-
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending on type
-        if debug.fog_off || map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
-            let mut bg = RGB::from_f32(0., 0., 0.);
-            match tile {
-                TileType::Wall => {
-                    glyph = wall_glyph(&*map, x, y);
-                    fg = RGB::from_f32(0., 1., 0.);
-                }
-                TileType::Floor => {
-                    glyph = to_cp437('.');
-                    fg = RGB::from_f32(0., 0.5, 0.5);
-                }
-                TileType::DownStairs => {
-                    glyph = to_cp437('<');
-                    fg = RGB::from_f32(0.7, 0.5, 0.3);
-                }
-            }
-            if !map.visible_tiles[idx] {
-                fg = fg.to_greyscale()
-            } else if map.bloodstains.contains(&idx) {
-                bg = RGB::from_f32(0.75, 0., 0.);
-            }
-            ctx.set(x, y, fg, bg, glyph);
+/// The glyph and colours a map tile is drawn with
+pub fn tile_glyph(idx: usize, map: &Map) -> (FontCharType, RGB, RGB) {
+    let mut glyph;
+    let mut fg;
+    let mut bg = RGB::from_f32(0., 0., 0.);
+    match map.tiles[idx] {
+        TileType::Wall => {
+            let x = idx as i32 % map.width;
+            let y = idx as i32 / map.width;
+            glyph = wall_glyph(map, x, y);
+            let (wall_fg, wall_bg) = map.theme.wall_colours();
+            fg = wall_fg;
+            bg = wall_bg;
         }
-
-        // Move the coordinates
-        x += 1;
-        if x > 79 {
-            x = 0;
-            y += 1;
+        TileType::Floor => {
+            glyph = map.theme.floor_glyph(idx);
+            let (floor_fg, floor_bg) = map.theme.floor_colours();
+            fg = floor_fg;
+            bg = floor_bg;
+        }
+        TileType::DownStairs => {
+            glyph = to_cp437('>');
+            fg = RGB::from_f32(0.7, 0.5, 0.3);
+        }
+        TileType::UpStairs => {
+            glyph = to_cp437('<');
+            fg = RGB::from_f32(0.7, 0.5, 0.3);
+        }
+        TileType::ShallowWater => {
+            glyph = to_cp437('~');
+            fg = RGB::from_f32(0.3, 0.6, 1.0);
+        }
+        TileType::DeepWater => {
+            glyph = to_cp437('≈');
+            fg = RGB::from_f32(0.1, 0.2, 0.9);
+        }
+        TileType::Lava => {
+            glyph = to_cp437('≈');
+            fg = RGB::from_f32(1.0, 0.35, 0.);
+            bg = RGB::from_f32(0.4, 0.05, 0.);
+        }
+        TileType::Chasm => {
+            glyph = to_cp437('░');
+            fg = RGB::from_f32(0.25, 0.2, 0.3);
         }
+        TileType::Rubble => {
+            glyph = to_cp437(';');
+            fg = RGB::from_f32(0.6, 0.5, 0.4);
+        }
+        TileType::Bridge => {
+            glyph = to_cp437('=');
+            fg = RGB::from_f32(0.6, 0.4, 0.2);
+        }
+    }
+    if let Some(decal) = map.decals.get(&idx) {
+        match decal.kind.glyph() {
+            Some(decal_glyph) if map.tiles[idx] == TileType::Floor => {
+                glyph = decal_glyph;
+                fg = decal.colour();
+            }
+            _ => bg = decal.colour(),
+        }
+    }
+    // Remembered tiles keep their decals, only without the colour
+    if map.visible_tiles[idx] {
+        let light = map.light_at(idx);
+        fg = shade(fg, light);
+        bg = shade(bg, light);
+    } else {
+        fg = map.theme.remembered(fg);
+        bg = map.theme.remembered(bg);
     }
+
+    (glyph, fg, bg)
 }
 
 pub fn try_next_level(ecs: &mut World) -> bool {
@@ -311,3 +437,18 @@ pub fn try_next_level(ecs: &mut World) -> bool {
         false
     }
 }
+
+pub fn try_previous_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] == TileType::UpStairs {
+        true
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no way up from here.".to_string());
+        false
+    }
+}
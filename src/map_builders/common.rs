@@ -0,0 +1,164 @@
+use std::{
+    cmp::{max, min},
+    collections::{BTreeMap, BTreeSet},
+};
+
+use bracket_lib::prelude::*;
+
+use crate::{
+    map::{Map, TileType},
+    rect::Rect,
+};
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum Symmetry {
+    None,
+    Horizontal,
+}
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in (room.y1 + 1)..=room.y2 {
+        for x in (room.x1 + 1)..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.tiles.len() {
+            map.tiles[idx as usize] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.tiles.len() {
+            map.tiles[idx as usize] = TileType::Floor;
+        }
+    }
+}
+
+/// Tiles where a corridor breaks through the wall around one of the rooms,
+/// which is where doors go
+pub fn room_doorways(map: &Map, rooms: &[Rect]) -> Vec<usize> {
+    let mut doorways = BTreeSet::new();
+    for room in rooms.iter() {
+        // The walls of a room run along x1 and y1, and just past x2 and y2
+        for x in (room.x1 + 1)..=room.x2 {
+            for y in [room.y1, room.y2 + 1].iter() {
+                if is_doorway(map, x, *y, (1, 0)) {
+                    doorways.insert(map.xy_idx(x, *y));
+                }
+            }
+        }
+        for y in (room.y1 + 1)..=room.y2 {
+            for x in [room.x1, room.x2 + 1].iter() {
+                if is_doorway(map, *x, y, (0, 1)) {
+                    doorways.insert(map.xy_idx(*x, y));
+                }
+            }
+        }
+    }
+    doorways.into_iter().collect()
+}
+
+/// A floor tile with wall on both sides of it along `wall_direction`
+fn is_doorway(map: &Map, x: i32, y: i32, wall_direction: (i32, i32)) -> bool {
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+        return false;
+    }
+    let (dx, dy) = wall_direction;
+    map.tiles[map.xy_idx(x, y)] == TileType::Floor
+        && map.tiles[map.xy_idx(x - dx, y - dy)] == TileType::Wall
+        && map.tiles[map.xy_idx(x + dx, y + dy)] == TileType::Wall
+}
+
+/// Walls off any floor (or other ground) that can't be reached from
+/// `start_idx`, and returns the index of the reachable floor tile that is
+/// furthest away from it.
+pub fn remove_unreachable_areas_returning_most_distant(map: &mut Map, start_idx: usize) -> usize {
+    map.populate_blocked();
+    let map_starts: Vec<usize> = vec![start_idx];
+    // Long enough for the longest path on the map, e.g. through a maze or lava
+    let max_depth = map.tiles.len() as f32 * TileType::Lava.movement_cost();
+    let dijkstra_map = DijkstraMap::new(map.width, map.height, &map_starts, map, max_depth);
+
+    let mut exit_tile = (start_idx, 0.0f32);
+    for (i, tile) in map.tiles.iter_mut().enumerate() {
+        // Nobody paths into a chasm, so they would all look cut off
+        if !tile.is_walkable() || *tile == TileType::Chasm {
+            continue;
+        }
+        let distance_to_start = dijkstra_map.map[i];
+        if distance_to_start == std::f32::MAX {
+            // We can't get to this tile, so wall it off
+            *tile = TileType::Wall;
+        } else if *tile == TileType::Floor && distance_to_start > exit_tile.1 {
+            exit_tile.0 = i;
+            exit_tile.1 = distance_to_start;
+        }
+    }
+
+    exit_tile.0
+}
+
+/// Splits the floor into regions using cellular noise, for spawning in maps without rooms
+pub fn generate_voronoi_spawn_regions(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+) -> BTreeMap<i32, Vec<usize>> {
+    let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    let mut noise = FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
+    noise.set_noise_type(NoiseType::Cellular);
+    noise.set_frequency(0.08);
+    noise.set_cellular_distance_function(CellularDistanceFunction::Manhattan);
+
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor {
+                let cell_value = noise.get_noise(x as f32, y as f32) * 10240.0;
+                noise_areas
+                    .entry(cell_value as i32)
+                    .or_insert_with(Vec::new)
+                    .push(idx);
+            }
+        }
+    }
+
+    noise_areas
+}
+
+/// Carves floor with a square brush, mirrored around the centre if `mode` asks for it
+pub fn paint(map: &mut Map, mode: Symmetry, brush_size: i32, x: i32, y: i32) {
+    match mode {
+        Symmetry::None => apply_paint(map, brush_size, x, y),
+        Symmetry::Horizontal => {
+            let center_x = map.width / 2;
+            if x == center_x {
+                apply_paint(map, brush_size, x, y);
+            } else {
+                let dist_x = i32::abs(center_x - x);
+                apply_paint(map, brush_size, center_x + dist_x, y);
+                apply_paint(map, brush_size, center_x - dist_x, y);
+            }
+        }
+    }
+}
+
+fn apply_paint(map: &mut Map, brush_size: i32, x: i32, y: i32) {
+    let half_brush_size = brush_size / 2;
+    for brush_y in y - half_brush_size..=y + half_brush_size {
+        for brush_x in x - half_brush_size..=x + half_brush_size {
+            if brush_x > 1 && brush_x < map.width - 1 && brush_y > 1 && brush_y < map.height - 1 {
+                let idx = map.xy_idx(brush_x, brush_y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use bracket_lib::prelude::*;
+
+use super::{common::remove_unreachable_areas_returning_most_distant, MapBuilder};
+use crate::{
+    components::Position,
+    map::{Map, TileType},
+    rect::Rect,
+};
+
+/// One of the last steps for every level: walls off anything the player
+/// can't walk to from the start, and puts the stairs down as far away as possible.
+/// Nothing spawns outside of what's left.
+pub struct CullUnreachableBuilder {
+    map: Map,
+    starting_position: Position,
+    history: Vec<Map>,
+    /// Leaves stairs that were placed by hand, as long as they can be reached
+    keep_stairs: bool,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for CullUnreachableBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for v in snapshot.revealed_tiles.iter_mut() {
+            *v = true;
+        }
+        self.history.push(snapshot);
+    }
+
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)> {
+        // Culled floor is wall now, and nothing should spawn on the stairs either
+        self.previous_builder
+            .get_spawn_list(rng)
+            .into_iter()
+            .filter(|(idx, _)| self.map.tiles[*idx] == TileType::Floor)
+            .collect()
+    }
+
+    fn get_rooms(&self) -> Vec<Rect> {
+        self.previous_builder.get_rooms()
+    }
+}
+
+impl CullUnreachableBuilder {
+    pub fn new(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> CullUnreachableBuilder {
+        CullUnreachableBuilder {
+            // Replaced by the previous builder's map
+            map: Map::new(new_depth, 0, 0),
+            starting_position: Position { x: 0, y: 0 },
+            history: Vec::new(),
+            keep_stairs: false,
+            previous_builder,
+        }
+    }
+
+    /// Culls a hand-made level, which has its own stairs down
+    pub fn keeping_stairs(
+        new_depth: i32,
+        previous_builder: Box<dyn MapBuilder>,
+    ) -> CullUnreachableBuilder {
+        CullUnreachableBuilder {
+            keep_stairs: true,
+            ..CullUnreachableBuilder::new(new_depth, previous_builder)
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        self.previous_builder.build_map(rng);
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.map = self.previous_builder.get_map();
+        self.history = self.previous_builder.get_snapshot_history();
+
+        // Wherever the stairs ended up, they are placed again below
+        if !self.keep_stairs {
+            for tile in self.map.tiles.iter_mut() {
+                if *tile == TileType::DownStairs {
+                    *tile = TileType::Floor;
+                }
+            }
+        }
+
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        // Stairs that can't be reached are walled up with everything else
+        if !self.map.tiles.contains(&TileType::DownStairs) {
+            self.map.tiles[exit_tile] = TileType::DownStairs;
+        }
+        // Nothing can lie on tiles that were walled up or flooded along the way
+        let tiles = &self.map.tiles;
+        self.map.decals.retain(|idx, _| tiles[*idx].holds_decals());
+        self.take_snapshot();
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+
+use bracket_lib::prelude::*;
+
+use super::{
+    common::{
+        generate_voronoi_spawn_regions, paint, remove_unreachable_areas_returning_most_distant,
+        Symmetry,
+    },
+    MapBuilder,
+};
+use crate::{
+    components::Position,
+    map::{Map, TileType},
+    spawner,
+};
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum DlaAlgorithm {
+    WalkInwards,
+    WalkOutwards,
+    CentralAttractor,
+}
+
+/// Diffusion-limited aggregation: particles wander until they bump into
+/// the existing floor, and stick where they land.
+pub struct DlaBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    history: Vec<Map>,
+    noise_areas: BTreeMap<i32, Vec<usize>>,
+    algorithm: DlaAlgorithm,
+    brush_size: i32,
+    symmetry: Symmetry,
+    floor_percent: f32,
+}
+
+impl MapBuilder for DlaBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for v in snapshot.revealed_tiles.iter_mut() {
+            *v = true;
+        }
+        self.history.push(snapshot);
+    }
+
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)> {
+        let mut spawn_list = Vec::new();
+        for area in self.noise_areas.values() {
+            spawner::spawn_region(rng, area, self.depth, &mut spawn_list);
+        }
+        spawn_list
+    }
+}
+
+impl DlaBuilder {
+    pub fn new(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        algorithm: DlaAlgorithm,
+        brush_size: i32,
+        symmetry: Symmetry,
+    ) -> DlaBuilder {
+        DlaBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            history: Vec::new(),
+            noise_areas: BTreeMap::new(),
+            algorithm,
+            brush_size,
+            symmetry,
+            floor_percent: 0.25,
+        }
+    }
+
+    /// Particles start at random spots and walk until they hit the floor
+    pub fn walk_inwards(new_depth: i32, width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            new_depth,
+            width,
+            height,
+            DlaAlgorithm::WalkInwards,
+            1,
+            Symmetry::None,
+        )
+    }
+
+    /// Particles start on the floor and walk until they dig into a wall
+    pub fn walk_outwards(new_depth: i32, width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            new_depth,
+            width,
+            height,
+            DlaAlgorithm::WalkOutwards,
+            2,
+            Symmetry::None,
+        )
+    }
+
+    /// Particles travel in a straight line towards the centre of the map
+    pub fn central_attractor(new_depth: i32, width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            new_depth,
+            width,
+            height,
+            DlaAlgorithm::CentralAttractor,
+            2,
+            Symmetry::Horizontal,
+        )
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Carve a starting seed
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        self.take_snapshot();
+        self.map.tiles[start_idx] = TileType::Floor;
+        self.map.tiles[start_idx - 1] = TileType::Floor;
+        self.map.tiles[start_idx + 1] = TileType::Floor;
+        self.map.tiles[start_idx - self.map.width as usize] = TileType::Floor;
+        self.map.tiles[start_idx + self.map.width as usize] = TileType::Floor;
+
+        // Random walker
+        let total_tiles = self.map.width * self.map.height;
+        let desired_floor_tiles = (self.floor_percent * total_tiles as f32) as usize;
+        let mut floor_tile_count = self.count_floor();
+        let mut i = 0;
+        while floor_tile_count < desired_floor_tiles {
+            match self.algorithm {
+                DlaAlgorithm::WalkInwards => self.walk_inwards_step(rng),
+                DlaAlgorithm::WalkOutwards => self.walk_outwards_step(rng),
+                DlaAlgorithm::CentralAttractor => self.central_attractor_step(rng),
+            }
+
+            if i % 10 == 0 {
+                self.take_snapshot();
+            }
+            i += 1;
+            floor_tile_count = self.count_floor();
+        }
+
+        // Find all tiles we can reach from the starting point, and put the exit furthest away
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        self.take_snapshot();
+
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+        self.take_snapshot();
+
+        self.noise_areas = generate_voronoi_spawn_regions(&self.map, rng);
+    }
+
+    fn walk_inwards_step(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut digger_x = rng.roll_dice(1, self.map.width - 3) + 1;
+        let mut digger_y = rng.roll_dice(1, self.map.height - 3) + 1;
+        let mut prev_x = digger_x;
+        let mut prev_y = digger_y;
+        let mut digger_idx = self.map.xy_idx(digger_x, digger_y);
+        while self.map.tiles[digger_idx] == TileType::Wall {
+            prev_x = digger_x;
+            prev_y = digger_y;
+            self.stagger(&mut digger_x, &mut digger_y, rng);
+            digger_idx = self.map.xy_idx(digger_x, digger_y);
+        }
+        paint(
+            &mut self.map,
+            self.symmetry,
+            self.brush_size,
+            prev_x,
+            prev_y,
+        );
+    }
+
+    fn walk_outwards_step(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut digger_x = self.starting_position.x;
+        let mut digger_y = self.starting_position.y;
+        let mut digger_idx = self.map.xy_idx(digger_x, digger_y);
+        while self.map.tiles[digger_idx] == TileType::Floor {
+            self.stagger(&mut digger_x, &mut digger_y, rng);
+            digger_idx = self.map.xy_idx(digger_x, digger_y);
+        }
+        paint(
+            &mut self.map,
+            self.symmetry,
+            self.brush_size,
+            digger_x,
+            digger_y,
+        );
+    }
+
+    fn central_attractor_step(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut digger_x = rng.roll_dice(1, self.map.width - 3) + 1;
+        let mut digger_y = rng.roll_dice(1, self.map.height - 3) + 1;
+        let mut prev_x = digger_x;
+        let mut prev_y = digger_y;
+        let mut digger_idx = self.map.xy_idx(digger_x, digger_y);
+
+        let mut path = line2d(
+            LineAlg::Bresenham,
+            Point::new(digger_x, digger_y),
+            Point::new(self.starting_position.x, self.starting_position.y),
+        );
+
+        while self.map.tiles[digger_idx] == TileType::Wall && !path.is_empty() {
+            prev_x = digger_x;
+            prev_y = digger_y;
+            digger_x = path[0].x;
+            digger_y = path[0].y;
+            path.remove(0);
+            digger_idx = self.map.xy_idx(digger_x, digger_y);
+        }
+        paint(
+            &mut self.map,
+            self.symmetry,
+            self.brush_size,
+            prev_x,
+            prev_y,
+        );
+    }
+
+    /// Moves the digger one step in a random direction, staying off the map edges
+    fn stagger(&self, x: &mut i32, y: &mut i32, rng: &mut RandomNumberGenerator) {
+        match rng.roll_dice(1, 4) {
+            1 => {
+                if *x > 2 {
+                    *x -= 1;
+                }
+            }
+            2 => {
+                if *x < self.map.width - 2 {
+                    *x += 1;
+                }
+            }
+            3 => {
+                if *y > 2 {
+                    *y -= 1;
+                }
+            }
+            _ => {
+                if *y < self.map.height - 2 {
+                    *y += 1;
+                }
+            }
+        }
+    }
+
+    fn count_floor(&self) -> usize {
+        self.map
+            .tiles
+            .iter()
+            .filter(|a| **a == TileType::Floor)
+            .count()
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use bracket_lib::prelude::*;
+
+use super::{
+    common::{generate_voronoi_spawn_regions, remove_unreachable_areas_returning_most_distant},
+    MapBuilder,
+};
+use crate::{
+    components::Position,
+    map::{Map, TileType},
+    spawner,
+};
+
+/// A perfect maze, carved with a recursive backtracker
+pub struct MazeBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    history: Vec<Map>,
+    noise_areas: BTreeMap<i32, Vec<usize>>,
+}
+
+impl MapBuilder for MazeBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for v in snapshot.revealed_tiles.iter_mut() {
+            *v = true;
+        }
+        self.history.push(snapshot);
+    }
+
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)> {
+        let mut spawn_list = Vec::new();
+        for area in self.noise_areas.values() {
+            spawner::spawn_region(rng, area, self.depth, &mut spawn_list);
+        }
+        spawn_list
+    }
+}
+
+impl MazeBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> MazeBuilder {
+        MazeBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            history: Vec::new(),
+            noise_areas: BTreeMap::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Each maze cell covers two map tiles, so the walls between cells
+        // get a tile of their own
+        let mut maze = Grid::new((self.map.width / 2) - 2, (self.map.height / 2) - 2);
+        maze.generate_maze(self, rng);
+
+        // The top left cell is always open
+        self.starting_position = Position { x: 2, y: 2 };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        self.take_snapshot();
+
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+        self.take_snapshot();
+
+        self.noise_areas = generate_voronoi_spawn_regions(&self.map, rng);
+    }
+}
+
+const TOP: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const LEFT: usize = 3;
+
+#[derive(Copy, Clone)]
+struct Cell {
+    row: i32,
+    column: i32,
+    walls: [bool; 4],
+    visited: bool,
+}
+
+impl Cell {
+    fn new(row: i32, column: i32) -> Cell {
+        Cell {
+            row,
+            column,
+            walls: [true, true, true, true],
+            visited: false,
+        }
+    }
+
+    fn remove_walls(&mut self, next: &mut Cell) {
+        let x = self.column - next.column;
+        let y = self.row - next.row;
+
+        if x == 1 {
+            self.walls[LEFT] = false;
+            next.walls[RIGHT] = false;
+        } else if x == -1 {
+            self.walls[RIGHT] = false;
+            next.walls[LEFT] = false;
+        } else if y == 1 {
+            self.walls[TOP] = false;
+            next.walls[BOTTOM] = false;
+        } else if y == -1 {
+            self.walls[BOTTOM] = false;
+            next.walls[TOP] = false;
+        }
+    }
+}
+
+struct Grid {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+    backtrace: Vec<usize>,
+    current: usize,
+}
+
+impl Grid {
+    fn new(width: i32, height: i32) -> Grid {
+        let mut cells = Vec::new();
+        for row in 0..height {
+            for column in 0..width {
+                cells.push(Cell::new(row, column));
+            }
+        }
+
+        Grid {
+            width,
+            height,
+            cells,
+            backtrace: Vec::new(),
+            current: 0,
+        }
+    }
+
+    fn calculate_index(&self, row: i32, column: i32) -> Option<usize> {
+        if row < 0 || column < 0 || column > self.width - 1 || row > self.height - 1 {
+            None
+        } else {
+            Some((column + (row * self.width)) as usize)
+        }
+    }
+
+    fn get_available_neighbors(&self) -> Vec<usize> {
+        let current_row = self.cells[self.current].row;
+        let current_column = self.cells[self.current].column;
+
+        [
+            self.calculate_index(current_row - 1, current_column),
+            self.calculate_index(current_row, current_column + 1),
+            self.calculate_index(current_row + 1, current_column),
+            self.calculate_index(current_row, current_column - 1),
+        ]
+        .iter()
+        .filter_map(|i| *i)
+        .filter(|i| !self.cells[*i].visited)
+        .collect()
+    }
+
+    fn find_next_cell(&self, rng: &mut RandomNumberGenerator) -> Option<usize> {
+        let neighbors = self.get_available_neighbors();
+        if neighbors.is_empty() {
+            return None;
+        }
+        let roll = (rng.roll_dice(1, neighbors.len() as i32) - 1) as usize;
+        Some(neighbors[roll])
+    }
+
+    fn generate_maze(&mut self, generator: &mut MazeBuilder, rng: &mut RandomNumberGenerator) {
+        let mut i = 0;
+        loop {
+            self.cells[self.current].visited = true;
+            match self.find_next_cell(rng) {
+                Some(next) => {
+                    self.cells[next].visited = true;
+                    self.backtrace.push(self.current);
+                    let mut current_cell = self.cells[self.current];
+                    let mut next_cell = self.cells[next];
+                    current_cell.remove_walls(&mut next_cell);
+                    self.cells[self.current] = current_cell;
+                    self.cells[next] = next_cell;
+                    self.current = next;
+                }
+                None => match self.backtrace.pop() {
+                    Some(previous) => self.current = previous,
+                    None => break,
+                },
+            }
+
+            if i % 50 == 0 {
+                self.copy_to_map(&mut generator.map);
+                generator.take_snapshot();
+            }
+            i += 1;
+        }
+        self.copy_to_map(&mut generator.map);
+        generator.take_snapshot();
+    }
+
+    fn copy_to_map(&self, map: &mut Map) {
+        // Clear the map
+        for tile in map.tiles.iter_mut() {
+            *tile = TileType::Wall;
+        }
+
+        for cell in self.cells.iter() {
+            let x = cell.column + 1;
+            let y = cell.row + 1;
+            let idx = map.xy_idx(x * 2, y * 2);
+
+            map.tiles[idx] = TileType::Floor;
+            if !cell.walls[TOP] {
+                map.tiles[idx - map.width as usize] = TileType::Floor
+            }
+            if !cell.walls[RIGHT] {
+                map.tiles[idx + 1] = TileType::Floor
+            }
+            if !cell.walls[BOTTOM] {
+                map.tiles[idx + map.width as usize] = TileType::Floor
+            }
+            if !cell.walls[LEFT] {
+                map.tiles[idx - 1] = TileType::Floor
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+use crate::{
+    components::Position,
+    map::Map,
+    random_table::RandomTable,
+    rect::Rect,
+    rng::{GameRng, RngStream},
+    spawner,
+};
+
+mod bsp_dungeon;
+mod cellular_automata;
+mod common;
+mod cull_unreachable;
+mod dla;
+mod drunkard;
+mod maze;
+mod prefab_builder;
+mod secrets;
+mod simple_map;
+mod terrain;
+mod torches;
+
+use bsp_dungeon::BspDungeonBuilder;
+use cellular_automata::CellularAutomataBuilder;
+use cull_unreachable::CullUnreachableBuilder;
+use dla::DlaBuilder;
+use drunkard::DrunkardsWalkBuilder;
+use maze::MazeBuilder;
+use prefab_builder::{prefab_levels, prefab_sections, PrefabBuilder};
+use secrets::SecretsBuilder;
+use simple_map::SimpleMapBuilder;
+use terrain::TerrainBuilder;
+use torches::TorchBuilder;
+
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    fn get_snapshot_history(&self) -> Vec<Map>;
+    fn take_snapshot(&mut self);
+    /// Rolls what should spawn on which tile, as `(map index, spawn name)` pairs
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)>;
+
+    /// The rooms the level was laid out with, if the algorithm uses any
+    fn get_rooms(&self) -> Vec<Rect> {
+        Vec::new()
+    }
+
+    fn spawn_entities(&self, ecs: &mut World) {
+        let spawn_list = {
+            let mut game_rng = ecs.write_resource::<GameRng>();
+            self.get_spawn_list(game_rng.stream(RngStream::Spawning))
+        };
+        let map = self.get_map();
+        for (idx, name) in spawn_list.iter() {
+            let position = Position {
+                x: *idx as i32 % map.width,
+                y: *idx as i32 / map.width,
+            };
+            spawner::spawn_named(ecs, name, position);
+        }
+    }
+}
+
+/// Picks a map generation algorithm for the level, the deeper the weirder
+pub fn random_builder(new_depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    let builder_table = RandomTable::new()
+        .add("Rooms and Corridors", 6)
+        .add("BSP Dungeon", 4)
+        .add("Cellular Automata", new_depth)
+        .add("Open Area", new_depth - 1)
+        .add("Open Halls", new_depth - 1)
+        .add("Winding Passages", new_depth - 2)
+        .add("Maze", new_depth - 3)
+        .add("DLA Walk Inwards", new_depth - 3)
+        .add("DLA Walk Outwards", new_depth - 3)
+        .add("DLA Central Attractor", new_depth - 4)
+        .add("Goblin Warrens", i32::min(new_depth - 2, 1));
+
+    let builder_name = builder_table.roll(rng);
+    let (width, height) = level_dimensions(&builder_name, new_depth, rng);
+    let mut builder: Box<dyn MapBuilder> = match builder_name.as_str() {
+        "BSP Dungeon" => Box::new(BspDungeonBuilder::new(new_depth, width, height)),
+        "Cellular Automata" => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+        "Open Area" => Box::new(DrunkardsWalkBuilder::open_area(new_depth, width, height)),
+        "Open Halls" => Box::new(DrunkardsWalkBuilder::open_halls(new_depth, width, height)),
+        "Winding Passages" => Box::new(DrunkardsWalkBuilder::winding_passages(
+            new_depth, width, height,
+        )),
+        "Maze" => Box::new(MazeBuilder::new(new_depth, width, height)),
+        "DLA Walk Inwards" => Box::new(DlaBuilder::walk_inwards(new_depth, width, height)),
+        "DLA Walk Outwards" => Box::new(DlaBuilder::walk_outwards(new_depth, width, height)),
+        "DLA Central Attractor" => {
+            Box::new(DlaBuilder::central_attractor(new_depth, width, height))
+        }
+        "Goblin Warrens" => Box::new(PrefabBuilder::level(
+            new_depth,
+            prefab_levels::GOBLIN_WARRENS,
+        )),
+        _ => Box::new(SimpleMapBuilder::new(new_depth, width, height)),
+    };
+
+    // Hand-made levels keep their layout and stairs: no sections, terrain or
+    // vaults are stamped over them, but they get the same final steps
+    let hand_made = builder_name == "Goblin Warrens";
+    if hand_made {
+        builder = Box::new(CullUnreachableBuilder::keeping_stairs(new_depth, builder));
+    } else {
+        builder = decorate(new_depth, builder, rng);
+        builder = Box::new(CullUnreachableBuilder::new(new_depth, builder));
+    }
+    builder = Box::new(SecretsBuilder::new(new_depth, builder));
+    Box::new(TorchBuilder::new(new_depth, builder))
+}
+
+/// Stamps sections, terrain and vaults over a generated level
+fn decorate(
+    new_depth: i32,
+    mut builder: Box<dyn MapBuilder>,
+    rng: &mut RandomNumberGenerator,
+) -> Box<dyn MapBuilder> {
+    if new_depth > 1 && rng.roll_dice(1, 10) == 1 {
+        builder = Box::new(PrefabBuilder::sectional(
+            new_depth,
+            prefab_sections::ORC_CAMP,
+            builder,
+        ));
+    }
+
+    // Rivers, rubble and slime can turn up anywhere, lava and chasms only further down
+    if rng.roll_dice(1, 4) == 1 {
+        builder = Box::new(TerrainBuilder::river(new_depth, builder));
+    }
+    if new_depth >= 5 && rng.roll_dice(1, 3) == 1 {
+        builder = Box::new(TerrainBuilder::lava_pools(new_depth, builder));
+    }
+    if new_depth >= 3 && rng.roll_dice(1, 6) == 1 {
+        builder = Box::new(TerrainBuilder::chasm(new_depth, builder));
+    }
+    if rng.roll_dice(1, 3) == 1 {
+        builder = Box::new(TerrainBuilder::rubble(new_depth, builder));
+    }
+    if rng.roll_dice(1, 4) == 1 {
+        builder = Box::new(TerrainBuilder::slime(new_depth, builder));
+    }
+
+    Box::new(PrefabBuilder::vaults(new_depth, builder))
+}
+
+/// Most levels are a little bigger than the screen, but deeper caves sprawl
+fn level_dimensions(
+    builder_name: &str,
+    new_depth: i32,
+    rng: &mut RandomNumberGenerator,
+) -> (i32, i32) {
+    match builder_name {
+        "Cellular Automata" | "Open Area" | "Open Halls" if new_depth >= 3 => {
+            (rng.range(120, 201), rng.range(70, 121))
+        }
+        _ => (80, 50),
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::HashSet;
+
+use bracket_lib::prelude::*;
+
+use super::{
+    common::{apply_horizontal_tunnel, apply_vertical_tunnel},
+    MapBuilder,
+};
+use crate::{
+    components::Position,
+    map::{Map, TileType},
+    map_text::glyph_meaning,
+    random_table::RandomTable,
+    rect::Rect,
+};
+
+pub mod prefab_levels;
+pub mod prefab_rooms;
+pub mod prefab_sections;
+
+use prefab_levels::PrefabLevel;
+use prefab_sections::PrefabSection;
+
+/// Where a prefab's layout comes from
+#[derive(Copy, Clone)]
+pub enum PrefabTemplate {
+    /// A REXPaint image; only the glyphs of the first layer are used
+    Rex(&'static [u8]),
+    /// Plain text, one line per row
+    Text(&'static str),
+}
+
+impl PrefabTemplate {
+    /// Reads the template into its width, height and glyphs, row by row
+    fn load(&self) -> (i32, i32, Vec<char>) {
+        match self {
+            PrefabTemplate::Rex(bytes) => {
+                let xp = XpFile::read(&mut &bytes[..]).expect("Unable to read REXPaint prefab");
+                let layer = &xp.layers[0];
+                let mut glyphs = Vec::with_capacity(layer.width * layer.height);
+                for y in 0..layer.height {
+                    for x in 0..layer.width {
+                        let cell = layer.get(x, y).unwrap();
+                        glyphs.push(std::char::from_u32(cell.ch).unwrap_or(' '));
+                    }
+                }
+                (layer.width as i32, layer.height as i32, glyphs)
+            }
+            PrefabTemplate::Text(text) => {
+                let lines: Vec<&str> = text.lines().collect();
+                let width = lines
+                    .iter()
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                let mut glyphs = Vec::with_capacity(width * lines.len());
+                for line in lines.iter() {
+                    let mut row: Vec<char> = line.chars().collect();
+                    // Editors like to strip trailing whitespace
+                    row.resize(width, ' ');
+                    glyphs.append(&mut row);
+                }
+                (width as i32, lines.len() as i32, glyphs)
+            }
+        }
+    }
+}
+
+/// The tile a prefab glyph becomes, and what spawns on it
+fn legend(glyph: char) -> (TileType, Option<String>) {
+    match glyph {
+        // Blank cells in REXPaint
+        ' ' | '\0' => (TileType::Floor, None),
+        _ => glyph_meaning(glyph).unwrap_or_else(|| {
+            console::log(format!("Unknown glyph in prefab: {:?}", glyph));
+            (TileType::Floor, None)
+        }),
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum PrefabMode {
+    /// A whole hand-made level
+    Level { level: PrefabLevel },
+    /// A piece stamped over a generated level
+    Sectional { section: PrefabSection },
+    /// A few small rooms stamped onto open floor of a generated level
+    RoomVaults,
+}
+
+pub struct PrefabBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    history: Vec<Map>,
+    mode: PrefabMode,
+    previous_builder: Option<Box<dyn MapBuilder>>,
+    spawns: Vec<(usize, String)>,
+    /// Tiles covered by prefabs, where the previous builder mustn't spawn anything
+    replaced_tiles: HashSet<usize>,
+}
+
+impl MapBuilder for PrefabBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for v in snapshot.revealed_tiles.iter_mut() {
+            *v = true;
+        }
+        self.history.push(snapshot);
+    }
+
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)> {
+        let mut spawn_list = match &self.previous_builder {
+            Some(previous_builder) => previous_builder
+                .get_spawn_list(rng)
+                .into_iter()
+                .filter(|(idx, _)| {
+                    !self.replaced_tiles.contains(idx) && self.map.tiles[*idx] == TileType::Floor
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        spawn_list.extend(self.spawns.iter().cloned());
+        spawn_list
+    }
+
+    fn get_rooms(&self) -> Vec<Rect> {
+        self.previous_builder
+            .as_ref()
+            .map_or_else(Vec::new, |previous_builder| previous_builder.get_rooms())
+    }
+}
+
+impl PrefabBuilder {
+    fn new(
+        new_depth: i32,
+        mode: PrefabMode,
+        previous_builder: Option<Box<dyn MapBuilder>>,
+    ) -> PrefabBuilder {
+        PrefabBuilder {
+            // Replaced by the prefab level or the previous builder's map
+            map: Map::new(new_depth, 0, 0),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            history: Vec::new(),
+            mode,
+            previous_builder,
+            spawns: Vec::new(),
+            replaced_tiles: HashSet::new(),
+        }
+    }
+
+    pub fn level(new_depth: i32, level: PrefabLevel) -> PrefabBuilder {
+        PrefabBuilder::new(new_depth, PrefabMode::Level { level }, None)
+    }
+
+    pub fn sectional(
+        new_depth: i32,
+        section: PrefabSection,
+        previous_builder: Box<dyn MapBuilder>,
+    ) -> PrefabBuilder {
+        PrefabBuilder::new(
+            new_depth,
+            PrefabMode::Sectional { section },
+            Some(previous_builder),
+        )
+    }
+
+    pub fn vaults(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> PrefabBuilder {
+        PrefabBuilder::new(new_depth, PrefabMode::RoomVaults, Some(previous_builder))
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        match self.mode {
+            PrefabMode::Level { level } => self.load_level(level),
+            PrefabMode::Sectional { section } => self.apply_sectional(section, rng),
+            PrefabMode::RoomVaults => self.apply_room_vaults(rng),
+        }
+        self.take_snapshot();
+    }
+
+    /// Builds the level this prefab is stamped onto
+    fn apply_previous_iteration(&mut self, rng: &mut RandomNumberGenerator) {
+        if let Some(previous_builder) = self.previous_builder.as_mut() {
+            previous_builder.build_map(rng);
+            self.starting_position = previous_builder.get_starting_position();
+            self.map = previous_builder.get_map();
+            self.history = previous_builder.get_snapshot_history();
+        }
+    }
+
+    fn stamp_glyph(&mut self, glyph: char, idx: usize) {
+        let (tile, spawn) = legend(glyph);
+        self.map.tiles[idx] = tile;
+        if let Some(name) = spawn {
+            self.spawns.push((idx, name));
+        }
+        self.replaced_tiles.insert(idx);
+    }
+
+    fn load_level(&mut self, level: PrefabLevel) {
+        let (width, height, glyphs) = level.template.load();
+        self.map = Map::new(self.depth, width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let glyph = glyphs[(y * width + x) as usize];
+                if glyph == '@' {
+                    self.starting_position = Position { x, y };
+                }
+                let idx = self.map.xy_idx(x, y);
+                self.stamp_glyph(glyph, idx);
+            }
+        }
+    }
+
+    fn apply_sectional(&mut self, section: PrefabSection, rng: &mut RandomNumberGenerator) {
+        self.apply_previous_iteration(rng);
+
+        let (width, height, glyphs) = section.template.load();
+        let chunk_x = rng.roll_dice(1, self.map.width - width - 2);
+        let chunk_y = rng.roll_dice(1, self.map.height - height - 2);
+
+        let mut section_tiles = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let idx = self.map.xy_idx(chunk_x + x, chunk_y + y);
+                self.stamp_glyph(glyphs[(y * width + x) as usize], idx);
+                section_tiles.push(idx);
+            }
+        }
+        self.take_snapshot();
+
+        self.reconnect(&section_tiles);
+    }
+
+    /// A section can cut a level up, so this makes sure the player still starts on
+    /// the floor and can walk into the section. Anything else it cut off is culled,
+    /// and the stairs placed, once the level is done.
+    fn reconnect(&mut self, section_tiles: &[usize]) {
+        let mut start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        if self.map.tiles[start_idx] == TileType::Wall {
+            start_idx = self.closest_tile(start_idx, |_, tile| tile != TileType::Wall);
+            self.starting_position = Position {
+                x: start_idx as i32 % self.map.width,
+                y: start_idx as i32 / self.map.width,
+            };
+        }
+
+        self.map.populate_blocked();
+        let dijkstra_map = DijkstraMap::new(
+            self.map.width,
+            self.map.height,
+            &[start_idx],
+            &self.map,
+            self.map.tiles.len() as f32,
+        );
+        let cut_off = section_tiles.iter().find(|idx| {
+            self.map.tiles[**idx] == TileType::Floor && dijkstra_map.map[**idx] == std::f32::MAX
+        });
+        if let Some(&cut_off) = cut_off {
+            let target = self.closest_tile(cut_off, |idx, _| {
+                dijkstra_map.map[idx] != std::f32::MAX && !section_tiles.contains(&idx)
+            });
+            let (x1, y1) = (
+                cut_off as i32 % self.map.width,
+                cut_off as i32 / self.map.width,
+            );
+            let (x2, y2) = (
+                target as i32 % self.map.width,
+                target as i32 / self.map.width,
+            );
+            apply_horizontal_tunnel(&mut self.map, x1, x2, y1);
+            apply_vertical_tunnel(&mut self.map, y1, y2, x2);
+            self.take_snapshot();
+        }
+    }
+
+    /// Finds the tile nearest to `from` that satisfies `predicate`
+    fn closest_tile<F>(&self, from: usize, predicate: F) -> usize
+    where
+        F: Fn(usize, TileType) -> bool,
+    {
+        let width = self.map.width as usize;
+        let origin = Point::new(from % width, from / width);
+        let mut closest = (from, std::f32::MAX);
+        for (idx, tile) in self.map.tiles.iter().enumerate() {
+            if predicate(idx, *tile) {
+                let distance = DistanceAlg::Pythagoras
+                    .distance2d(origin, Point::new(idx % width, idx / width));
+                if distance < closest.1 {
+                    closest = (idx, distance);
+                }
+            }
+        }
+        closest.0
+    }
+
+    fn apply_room_vaults(&mut self, rng: &mut RandomNumberGenerator) {
+        self.apply_previous_iteration(rng);
+
+        // Vaults are a treat, not every level gets them
+        let vault_roll = rng.roll_dice(1, 6) + self.depth;
+        if vault_roll < 4 {
+            return;
+        }
+
+        let mut possible_vaults = prefab_rooms::vaults_for_depth(self.depth);
+        let n_vaults = rng.roll_dice(1, 3);
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        let mut used_tiles: HashSet<usize> = HashSet::new();
+
+        for _ in 0..n_vaults {
+            if possible_vaults.is_empty() {
+                break;
+            }
+            let vault_table = possible_vaults
+                .iter()
+                .fold(RandomTable::new(), |table, vault| {
+                    table.add(vault.name, vault.weight)
+                });
+            let vault_name = vault_table.roll(rng);
+            let vault = match possible_vaults.iter().find(|v| v.name == vault_name) {
+                Some(vault) => *vault,
+                None => break,
+            };
+            // Only one of each vault per level
+            possible_vaults.retain(|v| v.name != vault_name);
+
+            let (width, height, glyphs) = vault.template.load();
+
+            // Vaults only go where there's nothing but open floor
+            let mut vault_positions: Vec<Position> = Vec::new();
+            for y in 1..self.map.height - height - 1 {
+                for x in 1..self.map.width - width - 1 {
+                    let mut possible = true;
+                    for ty in 0..height {
+                        for tx in 0..width {
+                            let idx = self.map.xy_idx(x + tx, y + ty);
+                            if self.map.tiles[idx] != TileType::Floor
+                                || used_tiles.contains(&idx)
+                                || idx == start_idx
+                            {
+                                possible = false;
+                            }
+                        }
+                    }
+                    if possible {
+                        vault_positions.push(Position { x, y });
+                    }
+                }
+            }
+
+            if vault_positions.is_empty() {
+                continue;
+            }
+            let position_index = (rng.roll_dice(1, vault_positions.len() as i32) - 1) as usize;
+            let pos = vault_positions[position_index].clone();
+            for ty in 0..height {
+                for tx in 0..width {
+                    let idx = self.map.xy_idx(pos.x + tx, pos.y + ty);
+                    self.stamp_glyph(glyphs[(ty * width + tx) as usize], idx);
+                    used_tiles.insert(idx);
+                }
+            }
+            self.take_snapshot();
+        }
+    }
+}
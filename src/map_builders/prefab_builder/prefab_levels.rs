@@ -0,0 +1,13 @@
+use super::PrefabTemplate;
+
+/// A complete hand-made level, including where the player starts
+#[derive(Copy, Clone)]
+pub struct PrefabLevel {
+    pub template: PrefabTemplate,
+}
+
+pub const GOBLIN_WARRENS: PrefabLevel = PrefabLevel {
+    template: PrefabTemplate::Rex(include_bytes!(
+        "../../../resources/prefabs/levels/goblin_warrens.xp"
+    )),
+};
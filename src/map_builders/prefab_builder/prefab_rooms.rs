@@ -0,0 +1,62 @@
+use super::PrefabTemplate;
+
+/// A small hand-made room, placed on open floor of a generated level
+#[derive(Copy, Clone)]
+pub struct PrefabRoom {
+    pub name: &'static str,
+    pub template: PrefabTemplate,
+    pub first_depth: i32,
+    pub last_depth: i32,
+    /// Rarity weight, relative to the other vaults available at the same depth
+    pub weight: i32,
+}
+
+pub const GOBLIN_AMBUSH: PrefabRoom = PrefabRoom {
+    name: "Goblin Ambush",
+    template: PrefabTemplate::Text(include_str!(
+        "../../../resources/prefabs/vaults/goblin_ambush.txt"
+    )),
+    first_depth: 1,
+    last_depth: 5,
+    weight: 10,
+};
+
+pub const PILLARED_SHRINE: PrefabRoom = PrefabRoom {
+    name: "Pillared Shrine",
+    template: PrefabTemplate::Text(include_str!(
+        "../../../resources/prefabs/vaults/pillared_shrine.txt"
+    )),
+    first_depth: 1,
+    last_depth: 100,
+    weight: 4,
+};
+
+pub const TRAPPED_CACHE: PrefabRoom = PrefabRoom {
+    name: "Trapped Cache",
+    template: PrefabTemplate::Text(include_str!(
+        "../../../resources/prefabs/vaults/trapped_cache.txt"
+    )),
+    first_depth: 2,
+    last_depth: 8,
+    weight: 6,
+};
+
+pub const ORC_GUARDROOM: PrefabRoom = PrefabRoom {
+    name: "Orc Guardroom",
+    template: PrefabTemplate::Text(include_str!(
+        "../../../resources/prefabs/vaults/orc_guardroom.txt"
+    )),
+    first_depth: 3,
+    last_depth: 100,
+    weight: 5,
+};
+
+const VAULTS: [PrefabRoom; 4] = [GOBLIN_AMBUSH, PILLARED_SHRINE, TRAPPED_CACHE, ORC_GUARDROOM];
+
+pub fn vaults_for_depth(depth: i32) -> Vec<PrefabRoom> {
+    VAULTS
+        .iter()
+        .filter(|vault| depth >= vault.first_depth && depth <= vault.last_depth)
+        .copied()
+        .collect()
+}
@@ -0,0 +1,13 @@
+use super::PrefabTemplate;
+
+/// A large piece of a level, stamped over whatever was generated underneath it
+#[derive(Copy, Clone)]
+pub struct PrefabSection {
+    pub template: PrefabTemplate,
+}
+
+pub const ORC_CAMP: PrefabSection = PrefabSection {
+    template: PrefabTemplate::Text(include_str!(
+        "../../../resources/prefabs/sections/orc_camp.txt"
+    )),
+};
@@ -0,0 +1,239 @@
+use bracket_lib::prelude::*;
+
+use super::MapBuilder;
+use crate::{
+    components::Position,
+    map::{Map, TileType},
+    rect::Rect,
+    spawner,
+};
+
+/// The longest stretch of rock a secret corridor is dug through
+const MAX_CORRIDOR_LENGTH: i32 = 10;
+
+const DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// Adds what can only be found by searching: corridors between parts of the
+/// level that are walled off at both ends by secret doors, and now and then a
+/// treasure room behind one. Secret doors stand in wall tiles, so this runs
+/// after culling, which would otherwise wall off everything behind them.
+pub struct SecretsBuilder {
+    map: Map,
+    starting_position: Position,
+    history: Vec<Map>,
+    secret_doors: Vec<usize>,
+    treasure_room: Option<Rect>,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for SecretsBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for v in snapshot.revealed_tiles.iter_mut() {
+            *v = true;
+        }
+        self.history.push(snapshot);
+    }
+
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)> {
+        let mut spawn_list = self.previous_builder.get_spawn_list(rng);
+        for idx in self.secret_doors.iter() {
+            spawn_list.push((*idx, "Secret Door".to_string()));
+        }
+        if let Some(room) = self.treasure_room {
+            let mut area = Vec::new();
+            for y in room.y1..=room.y2 {
+                for x in room.x1..=room.x2 {
+                    area.push(self.map.xy_idx(x, y));
+                }
+            }
+            spawner::spawn_treasure(rng, &area, self.map.depth, &mut spawn_list);
+        }
+        spawn_list
+    }
+
+    fn get_rooms(&self) -> Vec<Rect> {
+        self.previous_builder.get_rooms()
+    }
+}
+
+impl SecretsBuilder {
+    pub fn new(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> SecretsBuilder {
+        SecretsBuilder {
+            // Replaced by the previous builder's map
+            map: Map::new(new_depth, 0, 0),
+            starting_position: Position { x: 0, y: 0 },
+            history: Vec::new(),
+            secret_doors: Vec::new(),
+            treasure_room: None,
+            previous_builder,
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        self.previous_builder.build_map(rng);
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.map = self.previous_builder.get_map();
+        self.history = self.previous_builder.get_snapshot_history();
+
+        for _ in 0..rng.roll_dice(1, 3) - 1 {
+            self.dig_secret_corridor(rng);
+        }
+        if rng.roll_dice(1, 3) == 1 {
+            self.build_treasure_room(rng);
+        }
+    }
+
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        x > 0
+            && x < self.map.width - 1
+            && y > 0
+            && y < self.map.height - 1
+            && self.map.tiles[self.map.xy_idx(x, y)] == TileType::Wall
+    }
+
+    fn is_floor(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && x < self.map.width
+            && y >= 0
+            && y < self.map.height
+            && self.map.tiles[self.map.xy_idx(x, y)] == TileType::Floor
+    }
+
+    /// Wall tiles with floor on one side, along with the direction away from it
+    fn walls_facing_floor(&self) -> Vec<(Point, (i32, i32))> {
+        let mut walls = Vec::new();
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                if !self.is_wall(x, y) {
+                    continue;
+                }
+                for (dx, dy) in DIRECTIONS.iter() {
+                    if self.is_floor(x - dx, y - dy) {
+                        walls.push((Point::new(x, y), (*dx, *dy)));
+                    }
+                }
+            }
+        }
+        walls
+    }
+
+    /// Picks one of `candidates` at random, and removes it
+    fn take_random<T>(rng: &mut RandomNumberGenerator, candidates: &mut Vec<T>) -> T {
+        let index = (rng.roll_dice(1, candidates.len() as i32) - 1) as usize;
+        candidates.remove(index)
+    }
+
+    /// Digs a straight corridor through solid rock, from floor on one side to
+    /// floor on the other, with a secret door at each end
+    fn dig_secret_corridor(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut candidates = self.walls_facing_floor();
+        for _ in 0..20 {
+            if candidates.is_empty() {
+                return;
+            }
+            let (start, (dx, dy)) = Self::take_random(rng, &mut candidates);
+
+            // Walk into the rock until there's floor on the other side
+            let mut length = None;
+            for step in 1..=MAX_CORRIDOR_LENGTH {
+                let x = start.x + dx * step;
+                let y = start.y + dy * step;
+                if self.is_floor(x, y) {
+                    length = Some(step);
+                    break;
+                }
+                // Running alongside other open ground would give it away
+                if !self.is_wall(x, y)
+                    || !self.is_wall(x + dy, y + dx)
+                    || !self.is_wall(x - dy, y - dx)
+                {
+                    break;
+                }
+            }
+
+            // Shorter than that, and it's just a door
+            if let Some(length) = length.filter(|length| *length >= 3) {
+                for step in 1..length - 1 {
+                    let idx = self.map.xy_idx(start.x + dx * step, start.y + dy * step);
+                    self.map.tiles[idx] = TileType::Floor;
+                }
+                self.secret_doors.push(self.map.xy_idx(start.x, start.y));
+                self.secret_doors.push(
+                    self.map
+                        .xy_idx(start.x + dx * (length - 1), start.y + dy * (length - 1)),
+                );
+                self.take_snapshot();
+                return;
+            }
+        }
+    }
+
+    /// Carves out a small room in solid rock next to some floor, with a
+    /// secret door as the only way in
+    fn build_treasure_room(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut candidates = self.walls_facing_floor();
+        for _ in 0..20 {
+            if candidates.is_empty() {
+                return;
+            }
+            let (door, (dx, dy)) = Self::take_random(rng, &mut candidates);
+            let depth = rng.range(3, 6);
+            let breadth = rng.range(3, 6);
+
+            // The room starts just behind the door, centred on it
+            let (x1, y1, x2, y2) = if dx != 0 {
+                let x_near = door.x + dx;
+                let x_far = door.x + dx * depth;
+                (
+                    i32::min(x_near, x_far),
+                    door.y - breadth / 2,
+                    i32::max(x_near, x_far),
+                    door.y - breadth / 2 + breadth - 1,
+                )
+            } else {
+                let y_near = door.y + dy;
+                let y_far = door.y + dy * depth;
+                (
+                    door.x - breadth / 2,
+                    i32::min(y_near, y_far),
+                    door.x - breadth / 2 + breadth - 1,
+                    i32::max(y_near, y_far),
+                )
+            };
+
+            // The room and the walls around it have to be solid rock
+            let solid = (y1 - 1..=y2 + 1).all(|y| (x1 - 1..=x2 + 1).all(|x| self.is_wall(x, y)));
+            if !solid {
+                continue;
+            }
+
+            for y in y1..=y2 {
+                for x in x1..=x2 {
+                    let idx = self.map.xy_idx(x, y);
+                    self.map.tiles[idx] = TileType::Floor;
+                }
+            }
+            self.secret_doors.push(self.map.xy_idx(door.x, door.y));
+            self.treasure_room = Some(Rect { x1, y1, x2, y2 });
+            self.take_snapshot();
+            return;
+        }
+    }
+}
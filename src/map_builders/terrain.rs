@@ -0,0 +1,303 @@
+use bracket_lib::prelude::*;
+
+use super::MapBuilder;
+use crate::{
+    components::Position,
+    map::{DecalKind, Map, TileType},
+    rect::Rect,
+};
+
+/// Starting tiles closer than this are left alone, so nobody arrives in lava
+const SAFE_START_RADIUS: f32 = 6.0;
+
+#[derive(Copy, Clone)]
+pub enum TerrainFeature {
+    /// Water running from the top of the level to the bottom, with the odd bridge
+    River,
+    /// A few pools of lava on open floor
+    LavaPools,
+    /// A hole down to the next level
+    Chasm,
+    /// Patches of rough ground
+    Rubble,
+    /// Slippery puddles of slime
+    Slime,
+}
+
+/// Adds terrain on top of a level made by another builder
+pub struct TerrainBuilder {
+    map: Map,
+    starting_position: Position,
+    history: Vec<Map>,
+    feature: TerrainFeature,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for TerrainBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for v in snapshot.revealed_tiles.iter_mut() {
+            *v = true;
+        }
+        self.history.push(snapshot);
+    }
+
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)> {
+        self.previous_builder
+            .get_spawn_list(rng)
+            .into_iter()
+            .filter(|(idx, _)| self.map.tiles[*idx] == TileType::Floor)
+            .collect()
+    }
+
+    fn get_rooms(&self) -> Vec<Rect> {
+        self.previous_builder.get_rooms()
+    }
+}
+
+impl TerrainBuilder {
+    fn new(
+        new_depth: i32,
+        feature: TerrainFeature,
+        previous_builder: Box<dyn MapBuilder>,
+    ) -> TerrainBuilder {
+        TerrainBuilder {
+            // Replaced by the previous builder's map
+            map: Map::new(new_depth, 0, 0),
+            starting_position: Position { x: 0, y: 0 },
+            history: Vec::new(),
+            feature,
+            previous_builder,
+        }
+    }
+
+    pub fn river(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder::new(new_depth, TerrainFeature::River, previous_builder)
+    }
+
+    pub fn lava_pools(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder::new(new_depth, TerrainFeature::LavaPools, previous_builder)
+    }
+
+    pub fn chasm(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder::new(new_depth, TerrainFeature::Chasm, previous_builder)
+    }
+
+    pub fn rubble(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder::new(new_depth, TerrainFeature::Rubble, previous_builder)
+    }
+
+    pub fn slime(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder::new(new_depth, TerrainFeature::Slime, previous_builder)
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        self.previous_builder.build_map(rng);
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.map = self.previous_builder.get_map();
+        self.history = self.previous_builder.get_snapshot_history();
+
+        match self.feature {
+            TerrainFeature::River => self.build_river(rng),
+            TerrainFeature::LavaPools => {
+                for _ in 0..rng.roll_dice(1, 3) {
+                    let radius = rng.range(1, 3);
+                    self.build_pool(rng, TileType::Lava, radius);
+                }
+            }
+            TerrainFeature::Chasm => {
+                let radius = rng.range(2, 4);
+                self.build_pool(rng, TileType::Chasm, radius);
+            }
+            TerrainFeature::Rubble => {
+                for _ in 0..rng.roll_dice(1, 4) {
+                    self.build_rubble(rng);
+                }
+            }
+            TerrainFeature::Slime => {
+                for _ in 0..rng.roll_dice(1, 3) {
+                    self.build_slime(rng);
+                }
+            }
+        }
+    }
+
+    fn start_idx(&self) -> usize {
+        self.map
+            .xy_idx(self.starting_position.x, self.starting_position.y)
+    }
+
+    fn build_river(&mut self, rng: &mut RandomNumberGenerator) {
+        let start_idx = self.start_idx();
+        let mut x = rng.range(self.map.width / 4, self.map.width * 3 / 4);
+        let mut rows_since_bridge = 0;
+
+        for y in 1..self.map.height - 1 {
+            // Meander a little from row to row
+            x = i32::max(2, i32::min(self.map.width - 3, x + rng.range(-1, 2)));
+
+            // A bridge goes wherever the river crosses open floor, if there isn't one nearby
+            let crosses_floor = (x - 1..=x + 1)
+                .all(|bank_x| self.map.tiles[self.map.xy_idx(bank_x, y)] == TileType::Floor);
+            let bridge = crosses_floor && rows_since_bridge > 6;
+            rows_since_bridge = if bridge { 0 } else { rows_since_bridge + 1 };
+
+            for river_x in x - 1..=x + 1 {
+                let idx = self.map.xy_idx(river_x, y);
+                if idx == start_idx || is_stairs(self.map.tiles[idx]) {
+                    continue;
+                }
+                self.map.tiles[idx] = if bridge {
+                    TileType::Bridge
+                } else if river_x == x {
+                    TileType::DeepWater
+                } else {
+                    TileType::ShallowWater
+                };
+            }
+        }
+        self.take_snapshot();
+    }
+
+    /// Fills a roughly round patch of floor with `tile`, unless it would cut
+    /// the level in two
+    fn build_pool(&mut self, rng: &mut RandomNumberGenerator, tile: TileType, radius: i32) {
+        let center = match self.random_floor_away_from_start(rng) {
+            Some(center) => center,
+            None => return,
+        };
+
+        let reachable_before = self.count_safely_reachable();
+        let mut pool: Vec<usize> = Vec::new();
+        for y in center.y - radius..=center.y + radius {
+            for x in center.x - radius..=center.x + radius {
+                if x < 1 || x > self.map.width - 2 || y < 1 || y > self.map.height - 2 {
+                    continue;
+                }
+                let distance = DistanceAlg::Pythagoras.distance2d(center, Point::new(x, y));
+                let idx = self.map.xy_idx(x, y);
+                if distance <= radius as f32 + 0.5 && self.map.tiles[idx] == TileType::Floor {
+                    self.map.tiles[idx] = tile;
+                    pool.push(idx);
+                }
+            }
+        }
+
+        // Losing more than the pool itself means something got cut off
+        if self.count_safely_reachable() + pool.len() < reachable_before {
+            for idx in pool.iter() {
+                self.map.tiles[*idx] = TileType::Floor;
+            }
+            return;
+        }
+        self.take_snapshot();
+    }
+
+    fn build_rubble(&mut self, rng: &mut RandomNumberGenerator) {
+        let center = match self.random_floor_away_from_start(rng) {
+            Some(center) => center,
+            None => return,
+        };
+
+        for y in center.y - 2..=center.y + 2 {
+            for x in center.x - 2..=center.x + 2 {
+                if x < 1 || x > self.map.width - 2 || y < 1 || y > self.map.height - 2 {
+                    continue;
+                }
+                let idx = self.map.xy_idx(x, y);
+                if self.map.tiles[idx] == TileType::Floor && rng.roll_dice(1, 2) == 1 {
+                    self.map.tiles[idx] = TileType::Rubble;
+                }
+            }
+        }
+        self.take_snapshot();
+    }
+
+    /// Slime is a decal rather than a tile, it dries up eventually
+    fn build_slime(&mut self, rng: &mut RandomNumberGenerator) {
+        let center = match self.random_floor_away_from_start(rng) {
+            Some(center) => center,
+            None => return,
+        };
+
+        for y in center.y - 1..=center.y + 1 {
+            for x in center.x - 1..=center.x + 1 {
+                if x < 1 || x > self.map.width - 2 || y < 1 || y > self.map.height - 2 {
+                    continue;
+                }
+                let idx = self.map.xy_idx(x, y);
+                if self.map.tiles[idx] == TileType::Floor && rng.roll_dice(1, 3) > 1 {
+                    self.map.add_decal(idx, DecalKind::Slime);
+                }
+            }
+        }
+        self.take_snapshot();
+    }
+
+    fn random_floor_away_from_start(&self, rng: &mut RandomNumberGenerator) -> Option<Point> {
+        let start = Point::new(self.starting_position.x, self.starting_position.y);
+        let width = self.map.width as usize;
+        let candidates: Vec<Point> = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Floor)
+            .map(|(idx, _)| Point::new(idx % width, idx / width))
+            .filter(|pt| DistanceAlg::Pythagoras.distance2d(start, *pt) > SAFE_START_RADIUS)
+            .collect();
+
+        if candidates.is_empty() {
+            None
+        } else {
+            Some(candidates[(rng.roll_dice(1, candidates.len() as i32) - 1) as usize])
+        }
+    }
+
+    /// How many tiles can be reached from the start without wading through
+    /// lava or jumping into a chasm
+    fn count_safely_reachable(&mut self) -> usize {
+        self.map.populate_blocked();
+        for (idx, tile) in self.map.tiles.iter().enumerate() {
+            if *tile == TileType::Lava || *tile == TileType::Chasm {
+                self.map.blocked[idx] = true;
+            }
+        }
+
+        let start_idx = self.start_idx();
+        // Water makes paths more expensive than their length
+        let max_depth = self.map.tiles.len() as f32 * TileType::DeepWater.movement_cost();
+        let dijkstra_map = DijkstraMap::new(
+            self.map.width,
+            self.map.height,
+            &[start_idx],
+            &self.map,
+            max_depth,
+        );
+        dijkstra_map
+            .map
+            .iter()
+            .filter(|distance| **distance < std::f32::MAX)
+            .count()
+    }
+}
+
+fn is_stairs(tile: TileType) -> bool {
+    tile == TileType::DownStairs || tile == TileType::UpStairs
+}
@@ -0,0 +1,131 @@
+use bracket_lib::prelude::*;
+
+use super::MapBuilder;
+use crate::{
+    components::Position,
+    map::{Map, TileType},
+    rect::Rect,
+};
+
+/// Hangs torches on the walls of some of the rooms, the rest are left dark.
+/// The deeper the level, the fewer rooms are lit. Runs after culling, since
+/// torches go on walls.
+pub struct TorchBuilder {
+    map: Map,
+    starting_position: Position,
+    history: Vec<Map>,
+    torches: Vec<usize>,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for TorchBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for v in snapshot.revealed_tiles.iter_mut() {
+            *v = true;
+        }
+        self.history.push(snapshot);
+    }
+
+    fn get_spawn_list(&self, rng: &mut RandomNumberGenerator) -> Vec<(usize, String)> {
+        let mut spawn_list = self.previous_builder.get_spawn_list(rng);
+        spawn_list.extend(self.torches.iter().map(|idx| (*idx, "Torch".to_string())));
+        spawn_list
+    }
+
+    fn get_rooms(&self) -> Vec<Rect> {
+        self.previous_builder.get_rooms()
+    }
+}
+
+impl TorchBuilder {
+    pub fn new(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> TorchBuilder {
+        TorchBuilder {
+            // Replaced by the previous builder's map
+            map: Map::new(new_depth, 0, 0),
+            starting_position: Position { x: 0, y: 0 },
+            history: Vec::new(),
+            torches: Vec::new(),
+            previous_builder,
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        self.previous_builder.build_map(rng);
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.map = self.previous_builder.get_map();
+        self.history = self.previous_builder.get_snapshot_history();
+
+        let lit_chance = i32::max(20, 110 - self.map.depth * 15);
+        let rooms = self.previous_builder.get_rooms();
+        if rooms.is_empty() {
+            // Caves and the like have no rooms, so torches go on stretches of wall instead
+            let walls = self.walls_facing_ground(0, 0, self.map.width - 1, self.map.height - 1);
+            for _ in 0..walls.len() / 40 {
+                if rng.roll_dice(1, 100) <= lit_chance {
+                    self.place_torch(rng, &walls);
+                }
+            }
+        } else {
+            for room in rooms.iter() {
+                if rng.roll_dice(1, 100) > lit_chance {
+                    continue;
+                }
+                // The walls of a room run along x1 and y1, and just past x2 and y2
+                let walls = self.walls_facing_ground(room.x1, room.y1, room.x2 + 1, room.y2 + 1);
+                let area = (room.x2 - room.x1) * (room.y2 - room.y1);
+                for _ in 0..(1 + area / 40) {
+                    self.place_torch(rng, &walls);
+                }
+            }
+        }
+        self.take_snapshot();
+    }
+
+    fn place_torch(&mut self, rng: &mut RandomNumberGenerator, walls: &[usize]) {
+        if walls.is_empty() {
+            return;
+        }
+        let idx = walls[(rng.roll_dice(1, walls.len() as i32) - 1) as usize];
+        if !self.torches.contains(&idx) {
+            self.torches.push(idx);
+        }
+    }
+
+    /// Wall tiles within the rectangle with walkable ground on at least one side
+    fn walls_facing_ground(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> Vec<usize> {
+        let mut walls = Vec::new();
+        for y in i32::max(1, y1)..=i32::min(self.map.height - 2, y2) {
+            for x in i32::max(1, x1)..=i32::min(self.map.width - 2, x2) {
+                let idx = self.map.xy_idx(x, y);
+                if self.map.tiles[idx] != TileType::Wall {
+                    continue;
+                }
+                let faces_ground = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+                    let neighbour = self.map.xy_idx(x + dx, y + dy);
+                    self.map.tiles[neighbour].is_walkable()
+                });
+                if faces_ground {
+                    walls.push(idx);
+                }
+            }
+        }
+        walls
+    }
+}
@@ -0,0 +1,481 @@
+//! A plain-text format for levels, for writing them by hand and looking at
+//! them outside the game.
+//!
+//! A file starts with a header of `key: value` lines, which must include the
+//! `depth`, `width` and `height` of the level, and may name its `theme`. Doors
+//! are closed unless listed in `open_doors`, as space separated `x,y` pairs.
+//! Anything else in the header is kept as metadata.
+//!
+//! A blank line separates the header from the grid, which has one character
+//! per tile:
+//!
+//! ```text
+//! depth: 1
+//! width: 7
+//! height: 3
+//!
+//! #######
+//! #@.g.>#
+//! #######
+//! ```
+//!
+//! The glyphs are the same as in prefabs, see `resources/prefabs/README.md`.
+//! Anything that can be spawned is written as the `map_glyph` the raws give
+//! it, standing on floor, or set in a wall if the raws say it's `in_wall`.
+
+use std::collections::BTreeMap;
+
+use bracket_lib::prelude::*;
+use thiserror::Error;
+
+use crate::{
+    map::{Map, TileType},
+    raws::{RawMaster, Template, RAWS},
+    theme::Theme,
+};
+
+const TILE_GLYPHS: &[(char, TileType)] = &[
+    ('#', TileType::Wall),
+    ('.', TileType::Floor),
+    ('>', TileType::DownStairs),
+    ('<', TileType::UpStairs),
+    ('~', TileType::ShallowWater),
+    ('W', TileType::DeepWater),
+    ('L', TileType::Lava),
+    ('C', TileType::Chasm),
+    (';', TileType::Rubble),
+    ('=', TileType::Bridge),
+];
+
+const START_GLYPH: char = '@';
+
+#[derive(Error, Debug)]
+pub enum MapTextError {
+    #[error("header is missing `{0}`")]
+    MissingHeader(&'static str),
+    #[error("line {line} of the header isn't `key: value`")]
+    InvalidHeader { line: usize },
+    #[error("`{key}` should be a positive number, found `{value}`")]
+    InvalidNumber { key: &'static str, value: String },
+    #[error("expected {expected} rows, found {found}")]
+    WrongHeight { expected: i32, found: i32 },
+    #[error("row {row} is {found} tiles wide, expected {expected}")]
+    WrongWidth { row: i32, expected: i32, found: i32 },
+    #[error("unknown theme `{0}`")]
+    UnknownTheme(String),
+    #[error("unknown glyph `{glyph}` at {x},{y}")]
+    UnknownGlyph { glyph: char, x: i32, y: i32 },
+    #[error("`open_doors` should be `x,y` positions on the map, found `{0}`")]
+    InvalidOpenDoor(String),
+    #[error("`open_doors` lists {x},{y}, which isn't a door")]
+    NotADoor { x: i32, y: i32 },
+}
+
+/// Whether a glyph is taken by a tile, so the raws can't use it for a spawn
+pub fn is_reserved_glyph(glyph: char) -> bool {
+    glyph == START_GLYPH || TILE_GLYPHS.iter().any(|(c, _)| *c == glyph)
+}
+
+/// What a glyph stands for: the tile, and the name of whatever spawns on it
+pub fn glyph_meaning(glyph: char) -> Option<(TileType, Option<String>)> {
+    meaning(&RAWS.lock().unwrap(), glyph)
+}
+
+fn meaning(raws: &RawMaster, glyph: char) -> Option<(TileType, Option<String>)> {
+    if glyph == START_GLYPH {
+        return Some((TileType::Floor, None));
+    }
+    if let Some((_, tile)) = TILE_GLYPHS.iter().find(|(c, _)| *c == glyph) {
+        return Some((*tile, None));
+    }
+    raws.spawn_for_glyph(glyph)
+        .map(|name| (spawn_tile(raws, name), Some(name.to_string())))
+}
+
+/// The tile underneath something spawned
+fn spawn_tile(raws: &RawMaster, name: &str) -> TileType {
+    match raws.get(name) {
+        Some(Template::Prop(prop)) if prop.in_wall => TileType::Wall,
+        _ => TileType::Floor,
+    }
+}
+
+fn glyph_for_tile(tile: TileType) -> char {
+    TILE_GLYPHS
+        .iter()
+        .find(|(_, t)| *t == tile)
+        .map(|(c, _)| *c)
+        .unwrap_or('#')
+}
+
+/// A level, along with where the player starts and what spawns on it
+pub struct TextMap {
+    pub map: Map,
+    pub start: Option<Point>,
+    pub spawns: Vec<(usize, String)>,
+    /// The tiles of doors that are open
+    pub open_doors: Vec<usize>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl TextMap {
+    pub fn parse(text: &str) -> Result<TextMap, MapTextError> {
+        let mut lines = text.lines().enumerate();
+
+        let mut metadata = BTreeMap::new();
+        for (line_number, line) in &mut lines {
+            if line.trim().is_empty() {
+                break;
+            }
+            let mut parts = line.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => {
+                    metadata.insert(key.trim().to_string(), value.trim().to_string());
+                }
+                _ => {
+                    return Err(MapTextError::InvalidHeader {
+                        line: line_number + 1,
+                    })
+                }
+            }
+        }
+
+        let depth = take_number(&mut metadata, "depth")?;
+        let width = take_number(&mut metadata, "width")?;
+        let height = take_number(&mut metadata, "height")?;
+        let theme = match metadata.remove("theme") {
+            Some(name) => Theme::from_name(&name).ok_or(MapTextError::UnknownTheme(name))?,
+            None => Theme::for_depth(depth),
+        };
+
+        let open_doors = metadata.remove("open_doors").unwrap_or_default();
+
+        let mut rows: Vec<&str> = lines.map(|(_, line)| line).collect();
+        while rows.last().map_or(false, |row| row.is_empty()) {
+            rows.pop();
+        }
+        if rows.len() as i32 != height {
+            return Err(MapTextError::WrongHeight {
+                expected: height,
+                found: rows.len() as i32,
+            });
+        }
+
+        let raws = RAWS.lock().unwrap();
+        let mut map = Map::new(depth, width, height);
+        map.theme = theme;
+        let mut start = None;
+        let mut spawns = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            let y = y as i32;
+            let glyphs: Vec<char> = row.chars().collect();
+            if glyphs.len() as i32 != width {
+                return Err(MapTextError::WrongWidth {
+                    row: y,
+                    expected: width,
+                    found: glyphs.len() as i32,
+                });
+            }
+            for (x, glyph) in glyphs.into_iter().enumerate() {
+                let x = x as i32;
+                let (tile, spawn) =
+                    meaning(&raws, glyph).ok_or(MapTextError::UnknownGlyph { glyph, x, y })?;
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = tile;
+                if let Some(name) = spawn {
+                    spawns.push((idx, name));
+                }
+                if glyph == START_GLYPH {
+                    start = Some(Point::new(x, y));
+                }
+            }
+        }
+
+        let open_doors = open_doors
+            .split_whitespace()
+            .map(|pair| parse_position(&map, pair))
+            .collect::<Result<Vec<usize>, MapTextError>>()?;
+        for idx in open_doors.iter() {
+            let is_door = (spawns.iter())
+                .filter(|(spawn_idx, _)| spawn_idx == idx)
+                .any(|(_, name)| matches!(raws.get(name), Some(Template::Prop(prop)) if prop.door));
+            if !is_door {
+                return Err(MapTextError::NotADoor {
+                    x: *idx as i32 % width,
+                    y: *idx as i32 / width,
+                });
+            }
+        }
+
+        Ok(TextMap {
+            map,
+            start,
+            spawns,
+            open_doors,
+            metadata,
+        })
+    }
+
+    /// Writes the level out. Stairs and terrain take precedence over the
+    /// player or anything spawned on them, since a tile only has one glyph.
+    /// Spawns that can't be written are left out, with a warning.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "depth: {}\nwidth: {}\nheight: {}\ntheme: {}\n",
+            self.map.depth,
+            self.map.width,
+            self.map.height,
+            self.map.theme.name()
+        );
+        if !self.open_doors.is_empty() {
+            let positions: Vec<String> = (self.open_doors.iter())
+                .map(|idx| {
+                    format!(
+                        "{},{}",
+                        *idx as i32 % self.map.width,
+                        *idx as i32 / self.map.width
+                    )
+                })
+                .collect();
+            text.push_str(&format!("open_doors: {}\n", positions.join(" ")));
+        }
+        for (key, value) in self.metadata.iter() {
+            text.push_str(&format!("{}: {}\n", key, value));
+        }
+        text.push('\n');
+
+        let mut glyphs: Vec<char> = self.map.tiles.iter().map(|t| glyph_for_tile(*t)).collect();
+        let raws = RAWS.lock().unwrap();
+        for (idx, name) in self.spawns.iter() {
+            let glyph = match raws.get(name).and_then(|template| template.map_glyph()) {
+                Some(glyph) => glyph,
+                None => {
+                    console::log(format!("Map text: `{}` has no map glyph, left out", name));
+                    continue;
+                }
+            };
+            if self.map.tiles[*idx] == spawn_tile(&raws, name) {
+                glyphs[*idx] = glyph;
+            } else {
+                console::log(format!(
+                    "Map text: `{}` at {},{} isn't on a tile it can be written on, left out",
+                    name,
+                    *idx as i32 % self.map.width,
+                    *idx as i32 / self.map.width
+                ));
+            }
+        }
+        if let Some(start) = self.start {
+            let idx = self.map.xy_idx(start.x, start.y);
+            if self.map.tiles[idx] == TileType::Floor {
+                glyphs[idx] = START_GLYPH;
+            }
+        }
+
+        for row in glyphs.chunks(self.map.width as usize) {
+            text.extend(row.iter());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+fn parse_position(map: &Map, pair: &str) -> Result<usize, MapTextError> {
+    let invalid = || MapTextError::InvalidOpenDoor(pair.to_string());
+    let mut parts = pair.splitn(2, ',');
+    let x: i32 = parts
+        .next()
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(invalid)?;
+    let y: i32 = parts
+        .next()
+        .and_then(|y| y.parse().ok())
+        .ok_or_else(invalid)?;
+    if x < 0 || x >= map.width || y < 0 || y >= map.height {
+        return Err(invalid());
+    }
+    Ok(map.xy_idx(x, y))
+}
+
+fn take_number(
+    metadata: &mut BTreeMap<String, String>,
+    key: &'static str,
+) -> Result<i32, MapTextError> {
+    let value = metadata
+        .remove(key)
+        .ok_or(MapTextError::MissingHeader(key))?;
+    match value.parse::<i32>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(MapTextError::InvalidNumber { key, value }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raws::Raws;
+
+    /// Just what the maps below use, so the tests don't hang on what the
+    /// base raws or any installed mods happen to say
+    const FIXTURE: &str = r##"{
+        "mobs": [
+            {
+                "name": "Goblin",
+                "renderable": { "glyph": "g", "fg": "#FF0000", "bg": "#000000", "order": 1 },
+                "map_glyph": "g",
+                "monster_type": "Goblin",
+                "vision_range": 8,
+                "stats": { "hp": 16, "defence": 1, "power": 4 }
+            },
+            {
+                "name": "Orc",
+                "renderable": { "glyph": "o", "fg": "#FF0000", "bg": "#000000", "order": 1 },
+                "map_glyph": "o",
+                "monster_type": "Orc",
+                "vision_range": 8,
+                "stats": { "hp": 16, "defence": 2, "power": 6 }
+            }
+        ],
+        "items": [
+            {
+                "name": "Health Potion",
+                "renderable": { "glyph": ";", "fg": "#FF00FF", "bg": "#000000", "order": 2 },
+                "map_glyph": "!",
+                "consumable": true,
+                "effects": { "healing": 8 }
+            }
+        ],
+        "props": [
+            {
+                "name": "Door",
+                "renderable": { "glyph": "+", "fg": "#D2691E", "bg": "#000000", "order": 2 },
+                "map_glyph": "+",
+                "door": true
+            },
+            {
+                "name": "Secret Door",
+                "renderable": { "glyph": "+", "fg": "#D2691E", "bg": "#000000", "order": 2 },
+                "map_glyph": "S",
+                "in_wall": true,
+                "door": true,
+                "hidden": true
+            },
+            {
+                "name": "Torch",
+                "renderable": { "glyph": "*", "fg": "#FFA500", "bg": "#000000", "order": 2 },
+                "map_glyph": "t",
+                "in_wall": true
+            }
+        ],
+        "spawn_tables": {
+            "room": [{ "name": "Goblin", "weight": 1 }],
+            "treasure": [{ "name": "Health Potion", "weight": 1 }]
+        },
+        "names": ["Thomas"]
+    }"##;
+
+    fn load_fixture() {
+        let raws: Raws = serde_json::from_str(FIXTURE).expect("Fixture should parse");
+        *RAWS.lock().unwrap() = RawMaster::load(raws, Vec::new()).expect("Fixture should load");
+    }
+
+    fn round_trip(text: &str) -> String {
+        load_fixture();
+        TextMap::parse(text).expect("Map should parse").to_text()
+    }
+
+    #[test]
+    fn secret_doors_and_torches_stay_in_their_walls() {
+        let text = "depth: 1\nwidth: 9\nheight: 5\ntheme: Mines\n\n\
+                    #########\n\
+                    #@.g.#!.#\n\
+                    #....S..#\n\
+                    #t.....>#\n\
+                    #########\n";
+        assert_eq!(round_trip(text), text);
+
+        let level = TextMap::parse(text).unwrap();
+        let door = level.map.xy_idx(5, 2);
+        assert_eq!(level.map.tiles[door], TileType::Wall);
+        assert!(level.spawns.contains(&(door, "Secret Door".to_string())));
+    }
+
+    #[test]
+    fn dump_load_dump_is_unchanged() {
+        let text = "depth: 3\nwidth: 8\nheight: 5\ntheme: Crypt\nopen_doors: 3,2\nseed: 42\n\n\
+                    ########\n\
+                    #@.#.o.#\n\
+                    #.!+..;#\n\
+                    #..#~~>#\n\
+                    ########\n";
+        let dumped = round_trip(text);
+        assert_eq!(dumped, text);
+        assert_eq!(round_trip(&dumped), text);
+
+        let level = TextMap::parse(text).unwrap();
+        assert_eq!(level.map.depth, 3);
+        assert_eq!(level.map.theme, Theme::Crypt);
+        assert_eq!(level.start, Some(Point::new(1, 1)));
+        assert_eq!(level.open_doors, vec![level.map.xy_idx(3, 2)]);
+        assert_eq!(level.metadata.get("seed").map(String::as_str), Some("42"));
+    }
+
+    fn parse_error(text: &str) -> MapTextError {
+        load_fixture();
+        match TextMap::parse(text) {
+            Ok(_) => panic!("{:?} should not parse", text),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            parse_error("width: 1\nheight: 1\n\n."),
+            MapTextError::MissingHeader("depth")
+        ));
+        assert!(matches!(
+            parse_error("depth 1\n\n."),
+            MapTextError::InvalidHeader { line: 1 }
+        ));
+        assert!(matches!(
+            parse_error("depth: 0\nwidth: 1\nheight: 1\n\n."),
+            MapTextError::InvalidNumber { key: "depth", .. }
+        ));
+        assert!(matches!(
+            parse_error("depth: 1\nwidth: 1\nheight: 1\ntheme: Attic\n\n."),
+            MapTextError::UnknownTheme(_)
+        ));
+        assert!(matches!(
+            parse_error("depth: 1\nwidth: 2\nheight: 2\n\n.."),
+            MapTextError::WrongHeight {
+                expected: 2,
+                found: 1
+            }
+        ));
+        assert!(matches!(
+            parse_error("depth: 1\nwidth: 2\nheight: 2\n\n..\n."),
+            MapTextError::WrongWidth {
+                row: 1,
+                expected: 2,
+                found: 1
+            }
+        ));
+        assert!(matches!(
+            parse_error("depth: 1\nwidth: 2\nheight: 1\n\n.Q"),
+            MapTextError::UnknownGlyph {
+                glyph: 'Q',
+                x: 1,
+                y: 0
+            }
+        ));
+        assert!(matches!(
+            parse_error("depth: 1\nwidth: 2\nheight: 1\nopen_doors: 2,0\n\n.+"),
+            MapTextError::InvalidOpenDoor(_)
+        ));
+        assert!(matches!(
+            parse_error("depth: 1\nwidth: 2\nheight: 1\nopen_doors: 0,0\n\n.+"),
+            MapTextError::NotADoor { x: 0, y: 0 }
+        ));
+    }
+}
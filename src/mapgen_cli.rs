@@ -0,0 +1,228 @@
+//! `--generate`: builds levels without opening a window and reports on them,
+//! for tuning map generation and the spawn tables in bulk.
+//!
+//! ```text
+//! it_is_a_dungeon --generate [--count <n>] [--depth <d>] [--seed <s>] [--quiet]
+//! ```
+//!
+//! Level `i` is generated from seed `s + i`, exactly as the game would
+//! generate depth `d` of a world with that seed. `--quiet` leaves out the maps
+//! and only prints the statistics.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context};
+use bracket_lib::prelude::*;
+
+use crate::{
+    map::{Map, TileType},
+    map_builders,
+    map_text::TextMap,
+    rng::{GameRng, RngStream},
+};
+
+struct Options {
+    count: u64,
+    depth: i32,
+    seed: u64,
+    print_maps: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> anyhow::Result<Options> {
+        let mut options = Options {
+            count: 10,
+            depth: 1,
+            seed: GameRng::random_seed(),
+            print_maps: true,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--generate" => {}
+                "--quiet" => options.print_maps = false,
+                "--count" => options.count = parse_value(arg, args.next())?,
+                "--depth" => options.depth = parse_value(arg, args.next())?,
+                "--seed" => options.seed = parse_value(arg, args.next())?,
+                other => return Err(anyhow!("unknown argument `{}`", other)),
+            }
+        }
+
+        if options.depth < 1 {
+            return Err(anyhow!("--depth must be at least 1"));
+        }
+        Ok(options)
+    }
+}
+
+fn parse_value<T>(flag: &str, value: Option<&String>) -> anyhow::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = value.ok_or_else(|| anyhow!("{} needs a value", flag))?;
+    value
+        .parse()
+        .with_context(|| format!("invalid value `{}` for {}", value, flag))
+}
+
+/// What was measured on a single level
+struct LevelStats {
+    rooms: usize,
+    floor_ratio: f32,
+    path_to_stairs: Option<usize>,
+    unreachable: usize,
+}
+
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let options = Options::parse(args)?;
+
+    let mut all_stats: Vec<LevelStats> = Vec::new();
+    let mut spawn_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for i in 0..options.count {
+        let seed = options.seed.wrapping_add(i);
+        let mut rng = GameRng::new(seed);
+        rng.begin_level(options.depth);
+
+        let mut builder =
+            map_builders::random_builder(options.depth, rng.stream(RngStream::MapGen));
+        builder.build_map(rng.stream(RngStream::MapGen));
+        let mut map = builder.get_map();
+        let start = builder.get_starting_position();
+        let spawns = builder.get_spawn_list(rng.stream(RngStream::Spawning));
+
+        let stats = measure(
+            &mut map,
+            Point::new(start.x, start.y),
+            builder.get_rooms().len(),
+        );
+        println!(
+            "Level {}/{}, seed {}, depth {}, {}x{}",
+            i + 1,
+            options.count,
+            seed,
+            options.depth,
+            map.width,
+            map.height
+        );
+        if options.print_maps {
+            let level = TextMap {
+                map,
+                start: Some(Point::new(start.x, start.y)),
+                spawns: spawns.clone(),
+                open_doors: Vec::new(),
+                metadata: BTreeMap::new(),
+            };
+            println!("{}", level.to_text());
+        }
+        println!(
+            "rooms: {}, floor: {:.1}%, path to stairs: {}, unreachable tiles: {}\n",
+            stats.rooms,
+            stats.floor_ratio * 100.0,
+            stats
+                .path_to_stairs
+                .map_or_else(|| "none".to_string(), |steps| steps.to_string()),
+            stats.unreachable
+        );
+
+        for (_, name) in spawns {
+            *spawn_counts.entry(name).or_insert(0) += 1;
+        }
+        all_stats.push(stats);
+    }
+
+    print_summary(&options, &all_stats, &spawn_counts);
+    Ok(())
+}
+
+fn measure(map: &mut Map, start: Point, rooms: usize) -> LevelStats {
+    let walkable = map.tiles.iter().filter(|t| t.is_walkable()).count();
+
+    map.populate_blocked();
+    let start_idx = map.xy_idx(start.x, start.y);
+    let max_depth = map.tiles.len() as f32 * TileType::Lava.movement_cost();
+    let dijkstra_map = DijkstraMap::new(map.width, map.height, &[start_idx], &*map, max_depth);
+    // Nobody walks into a chasm by accident, so they don't count as cut off
+    let unreachable = map
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(idx, tile)| {
+            tile.is_walkable()
+                && **tile != TileType::Chasm
+                && dijkstra_map.map[*idx] == std::f32::MAX
+        })
+        .count();
+
+    let path_to_stairs = map
+        .tiles
+        .iter()
+        .position(|tile| *tile == TileType::DownStairs)
+        .and_then(|stairs_idx| {
+            let path = a_star_search(start_idx, stairs_idx, &*map);
+            if path.success {
+                Some(path.steps.len() - 1)
+            } else {
+                None
+            }
+        });
+
+    LevelStats {
+        rooms,
+        floor_ratio: walkable as f32 / map.tiles.len() as f32,
+        path_to_stairs,
+        unreachable,
+    }
+}
+
+fn print_summary(
+    options: &Options,
+    all_stats: &[LevelStats],
+    spawn_counts: &BTreeMap<String, usize>,
+) {
+    let levels = all_stats.len().max(1) as f32;
+    let average = |f: &dyn Fn(&LevelStats) -> f32| all_stats.iter().map(f).sum::<f32>() / levels;
+
+    let paths: Vec<usize> = all_stats.iter().filter_map(|s| s.path_to_stairs).collect();
+    let average_path = if paths.is_empty() {
+        0.0
+    } else {
+        paths.iter().sum::<usize>() as f32 / paths.len() as f32
+    };
+
+    println!(
+        "Summary of {} levels at depth {}, seeds {} to {}",
+        all_stats.len(),
+        options.depth,
+        options.seed,
+        options.seed.wrapping_add(options.count.saturating_sub(1))
+    );
+    println!("rooms:            {:.1}", average(&|s| s.rooms as f32));
+    println!(
+        "floor:            {:.1}%",
+        average(&|s| s.floor_ratio) * 100.0
+    );
+    println!(
+        "path to stairs:   {:.1} steps ({} levels without a path)",
+        average_path,
+        all_stats.len() - paths.len()
+    );
+    println!(
+        "unreachable:      {:.1} tiles",
+        average(&|s| s.unreachable as f32)
+    );
+
+    let total: usize = spawn_counts.values().sum();
+    println!("spawns:           {} in total", total);
+    let mut by_count: Vec<(&String, &usize)> = spawn_counts.iter().collect();
+    by_count.sort_by(|a, b| b.1.cmp(a.1));
+    for (name, count) in by_count {
+        println!(
+            "  {:<22} {:>6} {:>5.1}%",
+            name,
+            count,
+            *count as f32 / total as f32 * 100.0
+        );
+    }
+}
@@ -5,15 +5,23 @@ use specs::prelude::*;
 
 use crate::{
     components::{
-        CombatStats, EntityMoved, HungerClock, HungerState, Item, Monster, Player, Position,
-        Viewshed, WantsToMelee, WantsToPickupItem,
+        BlocksTile, BlocksVisibility, CombatStats, Door, EntityMoved, Hidden, HungerClock,
+        HungerState, Item, Monster, Name, Player, Position, Renderable, Vendor, Viewshed,
+        WantsToMelee, WantsToPickupItem,
     },
     gamelog::GameLog,
     map::Map,
+    rng::{GameRng, RngStream},
+    spawner,
+    systems::reveal_hidden,
     RunState, State,
 };
 
-fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// How far around the player a search reaches
+const SEARCH_RADIUS: i32 = 2;
+
+/// Moves the player, or attacks, opens or trades with whatever is in the way
+fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
@@ -22,6 +30,12 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let map = ecs.fetch::<Map>();
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut doors = ecs.write_storage::<Door>();
+    let mut blocks_movement = ecs.write_storage::<BlocksTile>();
+    let mut blocks_visibility = ecs.write_storage::<BlocksVisibility>();
+    let mut renderables = ecs.write_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let vendors = ecs.read_storage::<Vendor>();
 
     for (entity, _player, pos, viewshed) in
         (&entities, &mut players, &mut positions, &mut viewsheds).join()
@@ -31,11 +45,16 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             || pos.y + delta_y < 1
             || pos.y + delta_y > map.height - 1
         {
-            return;
+            return RunState::PlayerTurn;
         }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            if vendors.get(*potential_target).is_some() {
+                return RunState::ShowShopBuy {
+                    vendor: *potential_target,
+                };
+            }
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee
@@ -46,13 +65,33 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Add target failed");
-                return;
+                return RunState::PlayerTurn;
+            }
+            // A secret door is just more wall, until it's been found
+            if hidden.get(*potential_target).is_some() {
+                continue;
+            }
+            if doors
+                .get(*potential_target)
+                .map_or(false, |door| !door.open)
+            {
+                spawner::set_door_open(
+                    *potential_target,
+                    true,
+                    &mut doors,
+                    &mut blocks_movement,
+                    &mut blocks_visibility,
+                    &mut renderables,
+                );
+                viewshed.dirty = true;
+                return RunState::PlayerTurn;
             }
         }
 
         if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+            let from = Point::new(pos.x, pos.y);
+            pos.x = min(map.width - 1, max(0, pos.x + delta_x));
+            pos.y = min(map.height - 1, max(0, pos.y + delta_y));
 
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
@@ -60,10 +99,128 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
 
             viewshed.dirty = true;
             entity_moved
-                .insert(entity, EntityMoved {})
+                .insert(entity, EntityMoved { from })
                 .expect("Unable to insert marker");
         }
     }
+    RunState::PlayerTurn
+}
+
+/// Closes an open door next to the player, returns false if there was none
+fn try_close_door(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let player_entity = ecs.fetch::<Entity>();
+    let map = ecs.fetch::<Map>();
+    let mut doors = ecs.write_storage::<Door>();
+    let mut blocks_movement = ecs.write_storage::<BlocksTile>();
+    let mut blocks_visibility = ecs.write_storage::<BlocksVisibility>();
+    let mut renderables = ecs.write_storage::<Renderable>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let mut doorway_blocked = false;
+    for (dx, dy) in [
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ]
+    .iter()
+    {
+        let x = player_pos.x + dx;
+        let y = player_pos.y + dy;
+        if x < 0 || x > map.width - 1 || y < 0 || y > map.height - 1 {
+            continue;
+        }
+        let idx = map.xy_idx(x, y);
+        for entity in map.tile_content[idx].iter() {
+            if let Some(door) = doors.get(*entity) {
+                if !door.open {
+                    continue;
+                }
+                // Something standing or lying in the doorway keeps it open
+                if map.tile_content[idx].len() > 1 {
+                    doorway_blocked = true;
+                    continue;
+                }
+                spawner::set_door_open(
+                    *entity,
+                    false,
+                    &mut doors,
+                    &mut blocks_movement,
+                    &mut blocks_visibility,
+                    &mut renderables,
+                );
+                if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+                    viewshed.dirty = true;
+                }
+                gamelog.entries.push("You close the door.".to_string());
+                return true;
+            }
+        }
+    }
+
+    if doorway_blocked {
+        gamelog
+            .entries
+            .push("Something is in the way of the door.".to_string());
+    } else {
+        gamelog
+            .entries
+            .push("There is no open door next to you.".to_string());
+    }
+    false
+}
+
+/// Searches the area around the player for anything hidden, with much better
+/// odds than spotting it in passing
+fn search(ecs: &mut World) {
+    let player_pos = ecs.fetch::<Point>();
+    let mut map = ecs.fetch_mut::<Map>();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let doors = ecs.read_storage::<Door>();
+    let names = ecs.read_storage::<Name>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    let mut rng = ecs.fetch_mut::<GameRng>();
+
+    let mut found = false;
+    for y in player_pos.y - SEARCH_RADIUS..=player_pos.y + SEARCH_RADIUS {
+        for x in player_pos.x - SEARCH_RADIUS..=player_pos.x + SEARCH_RADIUS {
+            if x < 0 || x > map.width - 1 || y < 0 || y > map.height - 1 {
+                continue;
+            }
+            let idx = map.xy_idx(x, y);
+            let hidden_here: Vec<Entity> = map.tile_content[idx]
+                .iter()
+                .filter(|entity| hidden.get(**entity).is_some())
+                .copied()
+                .collect();
+            for entity in hidden_here {
+                if rng.stream(RngStream::Discovery).roll_dice(1, 3) == 1 {
+                    reveal_hidden(
+                        &mut map,
+                        idx,
+                        entity,
+                        &mut hidden,
+                        &doors,
+                        &names,
+                        &mut gamelog,
+                    );
+                    found = true;
+                }
+            }
+        }
+    }
+
+    if !found {
+        gamelog
+            .entries
+            .push("You search the area, but find nothing.".to_string());
+    }
 }
 
 fn get_item(ecs: &mut World) {
@@ -151,16 +308,16 @@ pub fn player_input(gs: &mut State, ctx: &mut BTerm) -> RunState {
         None => return RunState::AwaitingInput,
         Some(key) => match key {
             // Cardinals
-            Left | Numpad4 | H => try_move_player(-1, 0, &mut gs.ecs),
-            Right | Numpad6 | L => try_move_player(1, 0, &mut gs.ecs),
-            Up | Numpad8 | K => try_move_player(0, -1, &mut gs.ecs),
-            Down | Numpad2 | J => try_move_player(0, 1, &mut gs.ecs),
+            Left | Numpad4 | H => return try_move_player(-1, 0, &mut gs.ecs),
+            Right | Numpad6 | L => return try_move_player(1, 0, &mut gs.ecs),
+            Up | Numpad8 | K => return try_move_player(0, -1, &mut gs.ecs),
+            Down | Numpad2 | J => return try_move_player(0, 1, &mut gs.ecs),
 
             // Diagonals
-            Numpad9 | U => try_move_player(1, -1, &mut gs.ecs),
-            Numpad7 | Y => try_move_player(-1, -1, &mut gs.ecs),
-            Numpad3 | N => try_move_player(1, 1, &mut gs.ecs),
-            Numpad1 | B => try_move_player(-1, 1, &mut gs.ecs),
+            Numpad9 | U => return try_move_player(1, -1, &mut gs.ecs),
+            Numpad7 | Y => return try_move_player(-1, -1, &mut gs.ecs),
+            Numpad3 | N => return try_move_player(1, 1, &mut gs.ecs),
+            Numpad1 | B => return try_move_player(-1, 1, &mut gs.ecs),
 
             // Skip turn
             Space | Numpad5 => {
@@ -174,12 +331,25 @@ pub fn player_input(gs: &mut State, ctx: &mut BTerm) -> RunState {
             D => return RunState::ShowDropItem,
             // Drop item
             R => return RunState::ShowRemoveItem,
+            // Search for anything hidden nearby, takes a turn
+            S => search(&mut gs.ecs),
+            // Close door
+            C => {
+                if !try_close_door(&mut gs.ecs) {
+                    return RunState::AwaitingInput;
+                }
+            }
             // Level changes
             Period => {
                 if crate::map::try_next_level(&mut gs.ecs) {
                     return RunState::NextLevel;
                 }
             }
+            Comma => {
+                if crate::map::try_previous_level(&mut gs.ecs) {
+                    return RunState::PreviousLevel;
+                }
+            }
 
             Grave => return RunState::Console,
             Escape => return RunState::SaveGame,
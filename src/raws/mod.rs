@@ -0,0 +1,62 @@
+//! Monsters, items and props are described in `resources/raws/spawns.json`,
+//! along with the spawn tables that place them. The file is checked when the
+//! game starts, so a mistake in it is reported up front rather than showing up
+//! as something quietly missing from a level.
+//!
+//! Native builds read the file from disk, falling back to the copy compiled
+//! in, and reload it whenever it is saved. A file that doesn't check out is
+//! reported and the raws already loaded are kept.
+//!
+//! Mod packs in `mods/` are layered over the base raws, see `mods.rs`.
+
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+mod mods;
+mod rawmaster;
+mod templates;
+#[cfg(not(target_arch = "wasm32"))]
+mod watcher;
+
+pub use rawmaster::*;
+pub use templates::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use watcher::RawsWatcher;
+
+lazy_static! {
+    pub static ref RAWS: Mutex<RawMaster> = Mutex::new(RawMaster::empty());
+}
+
+pub const SPAWNS_PATH: &str = "./resources/raws/spawns.json";
+
+const SPAWNS_JSON: &str = include_str!("../../resources/raws/spawns.json");
+
+/// Loads the raws, replacing the ones in use only if the new ones are valid.
+/// Entities already spawned keep the stats they were created with.
+pub fn load_raws() -> Result<(), RawsError> {
+    let mut raws: Raws = serde_json::from_str(&read_spawns()?)?;
+    let mut labels = Vec::new();
+    for pack in mods::find_mods()? {
+        labels.push(pack.manifest.label());
+        raws.merge(pack.raws);
+    }
+    let raw_master = RawMaster::load(raws, labels)?;
+    *RAWS.lock().unwrap() = raw_master;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_spawns() -> Result<String, RawsError> {
+    match std::fs::read_to_string(SPAWNS_PATH) {
+        Ok(text) => Ok(text),
+        // Run from somewhere else, the game still has its own copy
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SPAWNS_JSON.to_string()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_spawns() -> Result<String, RawsError> {
+    Ok(SPAWNS_JSON.to_string())
+}
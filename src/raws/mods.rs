@@ -0,0 +1,114 @@
+//! A mod pack is a directory in `mods/` with a `mod.json` manifest, and
+//! optionally a `spawns.json` written like the base raws. Anything it defines
+//! replaces what the base raws, or a mod loaded before it, call the same name.
+//! Sound files are given relative to the pack's directory.
+
+use serde::Deserialize;
+
+use super::{Raws, RawsError};
+
+pub const MODS_DIR: &str = "./mods";
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ModManifest {
+    pub name: String,
+    pub version: String,
+    /// Packs are loaded lowest first, so higher ones win when they overlap
+    #[serde(default)]
+    pub load_order: i32,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+impl ModManifest {
+    /// How the pack is listed in the menu, and recorded in saves
+    pub fn label(&self) -> String {
+        format!("{} {}", self.name, self.version)
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+pub struct ModPack {
+    pub manifest: ModManifest,
+    pub raws: Raws,
+}
+
+/// The enabled packs, in the order they're loaded
+#[cfg(not(target_arch = "wasm32"))]
+pub fn find_mods() -> Result<Vec<ModPack>, RawsError> {
+    use std::{fs, io::ErrorKind};
+
+    let dirs = match fs::read_dir(MODS_DIR) {
+        Ok(dirs) => dirs,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut packs = Vec::new();
+    for dir in dirs {
+        let dir = dir?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        let pack_error = |source: RawsError| RawsError::Mod {
+            pack: dir.display().to_string(),
+            source: Box::new(source),
+        };
+
+        let manifest = match fs::read_to_string(dir.join("mod.json")) {
+            Ok(text) => {
+                serde_json::from_str::<ModManifest>(&text).map_err(|e| pack_error(e.into()))?
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(pack_error(RawsError::MissingManifest))
+            }
+            Err(e) => return Err(pack_error(e.into())),
+        };
+        if !manifest.enabled {
+            continue;
+        }
+
+        let mut raws = match fs::read_to_string(dir.join("spawns.json")) {
+            Ok(text) => serde_json::from_str::<Raws>(&text).map_err(|e| pack_error(e.into()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Raws::default(),
+            Err(e) => return Err(pack_error(e.into())),
+        };
+        for file in raws.sounds.values_mut() {
+            *file = dir.join(&file).display().to_string();
+        }
+        packs.push(ModPack { manifest, raws });
+    }
+
+    packs.sort_by(|a, b| {
+        (a.manifest.load_order, &a.manifest.name).cmp(&(b.manifest.load_order, &b.manifest.name))
+    });
+    Ok(packs)
+}
+
+/// Every file a pack is loaded from, in a fixed order. Packs that aren't
+/// enabled are included, since enabling one changes the raws too.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn mod_files() -> Vec<std::path::PathBuf> {
+    let dirs = match std::fs::read_dir(MODS_DIR) {
+        Ok(dirs) => dirs,
+        Err(_) => return Vec::new(),
+    };
+    let mut dirs: Vec<std::path::PathBuf> = dirs
+        .filter_map(|dir| dir.ok().map(|dir| dir.path()))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    dirs.sort();
+    dirs.iter()
+        .flat_map(|dir| vec![dir.join("mod.json"), dir.join("spawns.json")])
+        .collect()
+}
+
+/// There's no `mods/` in the browser
+#[cfg(target_arch = "wasm32")]
+pub fn find_mods() -> Result<Vec<ModPack>, RawsError> {
+    Ok(Vec::new())
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+
+use bracket_lib::random::RandomNumberGenerator;
+use thiserror::Error;
+
+use super::templates::{
+    ItemTemplate, MobTemplate, PropTemplate, Raws, RenderableTemplate, VendorTemplate,
+};
+use crate::{components::Corpse, map_text, random_table::RandomTable};
+
+/// The spawn tables level generation rolls on
+const REQUIRED_TABLES: &[&str] = &["room", "treasure"];
+
+#[derive(Error, Debug)]
+pub enum RawsError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("`{0}` is defined more than once")]
+    DuplicateName(String),
+    #[error("`{name}`: {reason}")]
+    InvalidTemplate { name: String, reason: &'static str },
+    #[error("`{mob}`: a corpse's `rot_turns` must be more than {min}, when it starts to rot")]
+    InvalidRotTurns { mob: String, min: i32 },
+    #[error("`{mob}` drops loot from `{table}`, which isn't defined")]
+    UnknownLootTable { mob: String, table: String },
+    #[error("loot table `{table}`: `{name}` isn't an item")]
+    InvalidLoot { table: String, name: String },
+    #[error("loot table `{table}`: {reason}")]
+    InvalidLootTable { table: String, reason: &'static str },
+    #[error("there are monsters or vendors, but no `names` to give them")]
+    NoNames,
+    #[error("no mod.json manifest")]
+    MissingManifest,
+    #[error("mod `{pack}`: {source}")]
+    Mod {
+        pack: String,
+        #[source]
+        source: Box<RawsError>,
+    },
+    #[error("`{name}` and `{other}` are both drawn as `{glyph}` in maps")]
+    DuplicateMapGlyph {
+        glyph: char,
+        name: String,
+        other: String,
+    },
+    #[error("spawn table `{0}` is missing")]
+    MissingTable(&'static str),
+    #[error("`{vendor}` sells from `{table}`, which isn't a spawn table")]
+    UnknownStock { vendor: String, table: String },
+    #[error("`{vendor}` can only sell items, but `{table}` lists `{name}`")]
+    InvalidStock {
+        vendor: String,
+        table: String,
+        name: String,
+    },
+    #[error("spawn table `{table}` lists `{name}`, which isn't defined")]
+    UnknownSpawn { table: String, name: String },
+    #[error("spawn table `{table}`: `{name}` has depth range {min} to {max}")]
+    InvalidDepthRange {
+        table: String,
+        name: String,
+        min: i32,
+        max: i32,
+    },
+    #[error("spawn table `{table}`: `{name}` has no weight at depth {depth}")]
+    InvalidWeight {
+        table: String,
+        name: String,
+        depth: i32,
+    },
+}
+
+/// A template found by name
+pub enum Template<'a> {
+    Mob(&'a MobTemplate),
+    Item(&'a ItemTemplate),
+    Prop(&'a PropTemplate),
+    Vendor(&'a VendorTemplate),
+}
+
+impl Template<'_> {
+    pub fn map_glyph(&self) -> Option<char> {
+        match self {
+            Template::Mob(mob) => mob.map_glyph,
+            Template::Item(item) => item.map_glyph,
+            Template::Prop(prop) => prop.map_glyph,
+            Template::Vendor(vendor) => vendor.map_glyph,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum TemplateIndex {
+    Mob(usize),
+    Item(usize),
+    Prop(usize),
+    Vendor(usize),
+}
+
+/// Loaded and validated raws, with templates indexed by name
+pub struct RawMaster {
+    raws: Raws,
+    index: HashMap<String, TemplateIndex>,
+    /// What each glyph in prefabs and text maps spawns
+    map_glyphs: HashMap<char, String>,
+    /// The mods layered over the base raws, as `name version`
+    mods: Vec<String>,
+}
+
+impl RawMaster {
+    pub fn empty() -> RawMaster {
+        RawMaster {
+            raws: Raws::default(),
+            index: HashMap::new(),
+            map_glyphs: HashMap::new(),
+            mods: Vec::new(),
+        }
+    }
+
+    pub fn load(raws: Raws, mods: Vec<String>) -> Result<RawMaster, RawsError> {
+        let mut index = HashMap::new();
+        let names = (raws.mobs.iter().enumerate())
+            .map(|(i, mob)| (&mob.name, TemplateIndex::Mob(i)))
+            .chain(
+                (raws.items.iter().enumerate())
+                    .map(|(i, item)| (&item.name, TemplateIndex::Item(i))),
+            )
+            .chain(
+                (raws.props.iter().enumerate())
+                    .map(|(i, prop)| (&prop.name, TemplateIndex::Prop(i))),
+            )
+            .chain(
+                (raws.vendors.iter().enumerate())
+                    .map(|(i, vendor)| (&vendor.name, TemplateIndex::Vendor(i))),
+            );
+        for (name, template) in names {
+            if index.insert(name.clone(), template).is_some() {
+                return Err(RawsError::DuplicateName(name.clone()));
+            }
+        }
+
+        for mob in raws.mobs.iter() {
+            validate_renderable(&mob.name, &mob.renderable)?;
+            if mob.stats.hp <= 0 {
+                return Err(invalid(&mob.name, "`hp` must be positive"));
+            }
+            if mob.vision_range < 1 {
+                return Err(invalid(&mob.name, "`vision_range` must be positive"));
+            }
+            if let Some(corpse) = &mob.corpse {
+                // A corpse that started out rotten would never get its rotting name
+                if corpse.rot_turns <= Corpse::ROTTEN_TURNS {
+                    return Err(RawsError::InvalidRotTurns {
+                        mob: mob.name.clone(),
+                        min: Corpse::ROTTEN_TURNS,
+                    });
+                }
+                if corpse.poison < 0 || corpse.weight < 0.0 {
+                    return Err(invalid(
+                        &mob.name,
+                        "a corpse's `poison` and `weight` can't be negative",
+                    ));
+                }
+            }
+            for item in mob.equipment.iter() {
+                if !matches!(index.get(item), Some(TemplateIndex::Item(_))) {
+                    return Err(invalid(&mob.name, "`equipment` can only list items"));
+                }
+            }
+            if let Some(table) = &mob.loot {
+                if !raws.loot_tables.contains_key(table) {
+                    return Err(RawsError::UnknownLootTable {
+                        mob: mob.name.clone(),
+                        table: table.clone(),
+                    });
+                }
+            }
+        }
+        for item in raws.items.iter() {
+            validate_renderable(&item.name, &item.renderable)?;
+            let effects = &item.effects;
+            if effects.ranged.is_none()
+                && (effects.damage.is_some()
+                    || effects.confusion.is_some()
+                    || effects.area_of_effect.is_some())
+            {
+                return Err(invalid(
+                    &item.name,
+                    "`damage`, `confusion` and `area_of_effect` need a `ranged` target",
+                ));
+            }
+            if item.equippable.is_some() && item.consumable {
+                return Err(invalid(
+                    &item.name,
+                    "can't be both equippable and consumable",
+                ));
+            }
+            if item.stackable && !item.consumable {
+                return Err(invalid(&item.name, "only consumables can be `stackable`"));
+            }
+            if item.value < 0 || item.gold.map_or(false, |gold| gold < 1) {
+                return Err(invalid(
+                    &item.name,
+                    "`value` can't be negative, and `gold` must be positive",
+                ));
+            }
+            if item.weight < 0.0 {
+                return Err(invalid(&item.name, "`weight` can't be negative"));
+            }
+        }
+        for prop in raws.props.iter() {
+            validate_renderable(&prop.name, &prop.renderable)?;
+        }
+        for vendor in raws.vendors.iter() {
+            validate_renderable(&vendor.name, &vendor.renderable)?;
+            if vendor.stock_count < 1 {
+                return Err(invalid(&vendor.name, "`stock_count` must be positive"));
+            }
+            let stock = match raws.spawn_tables.get(&vendor.stock) {
+                Some(stock) => stock,
+                None => {
+                    return Err(RawsError::UnknownStock {
+                        vendor: vendor.name.clone(),
+                        table: vendor.stock.clone(),
+                    })
+                }
+            };
+            for entry in stock.iter() {
+                if !matches!(index.get(&entry.name), Some(TemplateIndex::Item(_))) {
+                    return Err(RawsError::InvalidStock {
+                        vendor: vendor.name.clone(),
+                        table: vendor.stock.clone(),
+                        name: entry.name.clone(),
+                    });
+                }
+            }
+        }
+        if !(raws.mobs.is_empty() && raws.vendors.is_empty()) && raws.names.is_empty() {
+            return Err(RawsError::NoNames);
+        }
+
+        for (table, loot) in raws.loot_tables.iter() {
+            if loot.drop_chance < 0 || loot.drop_chance > 100 {
+                return Err(RawsError::InvalidLootTable {
+                    table: table.clone(),
+                    reason: "`drop_chance` is a percentage, from 0 to 100",
+                });
+            }
+            if loot.drops.iter().any(|entry| entry.weight < 1) {
+                return Err(RawsError::InvalidLootTable {
+                    table: table.clone(),
+                    reason: "every drop needs a weight of 1 or more",
+                });
+            }
+            let names = (loot.drops.iter().map(|entry| &entry.name)).chain(loot.guaranteed.iter());
+            for name in names {
+                let is_item = matches!(index.get(name), Some(TemplateIndex::Item(_)));
+                if !is_item {
+                    return Err(RawsError::InvalidLoot {
+                        table: table.clone(),
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+
+        for table in REQUIRED_TABLES {
+            if !raws.spawn_tables.contains_key(*table) {
+                return Err(RawsError::MissingTable(table));
+            }
+        }
+        for (table, entries) in raws.spawn_tables.iter() {
+            for entry in entries.iter() {
+                if !index.contains_key(&entry.name) {
+                    return Err(RawsError::UnknownSpawn {
+                        table: table.clone(),
+                        name: entry.name.clone(),
+                    });
+                }
+                let max = entry.max_depth.unwrap_or(entry.min_depth);
+                if entry.min_depth < 1 || max < entry.min_depth {
+                    return Err(RawsError::InvalidDepthRange {
+                        table: table.clone(),
+                        name: entry.name.clone(),
+                        min: entry.min_depth,
+                        max,
+                    });
+                }
+                // Weights below one are allowed when the depth makes up for it
+                if entry.weight_at(entry.min_depth) <= 0 {
+                    return Err(RawsError::InvalidWeight {
+                        table: table.clone(),
+                        name: entry.name.clone(),
+                        depth: entry.min_depth,
+                    });
+                }
+            }
+        }
+
+        let mut raw_master = RawMaster {
+            raws,
+            index,
+            map_glyphs: HashMap::new(),
+            mods,
+        };
+        let mut map_glyphs = HashMap::new();
+        for name in raw_master.keys() {
+            let glyph = match raw_master.get(name).and_then(|t| t.map_glyph()) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            if map_text::is_reserved_glyph(glyph) {
+                return Err(invalid(name, "`map_glyph` is already used for a tile"));
+            }
+            if let Some(other) = map_glyphs.insert(glyph, name.to_string()) {
+                return Err(RawsError::DuplicateMapGlyph {
+                    glyph,
+                    name: name.to_string(),
+                    other,
+                });
+            }
+        }
+        raw_master.map_glyphs = map_glyphs;
+        Ok(raw_master)
+    }
+
+    pub fn get(&self, key: &str) -> Option<Template<'_>> {
+        self.index.get(key).map(|template| match *template {
+            TemplateIndex::Mob(i) => Template::Mob(&self.raws.mobs[i]),
+            TemplateIndex::Item(i) => Template::Item(&self.raws.items[i]),
+            TemplateIndex::Prop(i) => Template::Prop(&self.raws.props[i]),
+            TemplateIndex::Vendor(i) => Template::Vendor(&self.raws.vendors[i]),
+        })
+    }
+
+    /// What a glyph in a prefab or text map spawns
+    pub fn spawn_for_glyph(&self, glyph: char) -> Option<&str> {
+        self.map_glyphs.get(&glyph).map(|name| name.as_str())
+    }
+
+    /// Finds the key a name refers to, ignoring case and spaces
+    pub fn find_key(&self, name: &str) -> Option<&str> {
+        let name = name.replace(' ', "");
+        self.index
+            .keys()
+            .find(|key| key.replace(' ', "").eq_ignore_ascii_case(&name))
+            .map(|key| key.as_str())
+    }
+
+    /// Every name that can be spawned, in alphabetical order
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.index.keys().map(|key| key.as_str()).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    pub fn mods(&self) -> &[String] {
+        &self.mods
+    }
+
+    pub fn name_table(&self) -> RandomTable {
+        (self.raws.names.iter()).fold(RandomTable::new(), |table, name| table.add(name, 1))
+    }
+
+    /// The file to load for a sound, which a mod may have replaced
+    pub fn sound_file(&self, path: &str) -> String {
+        match self.raws.sounds.get(path) {
+            Some(replacement) => replacement.clone(),
+            None => path.to_string(),
+        }
+    }
+
+    /// Every sound the raws name, whether they replace a built in one or a
+    /// mod adds it
+    pub fn sounds(&self) -> Vec<String> {
+        self.raws.sounds.keys().cloned().collect()
+    }
+
+    /// Everyone who can run a shop
+    pub fn vendors(&self) -> Vec<String> {
+        (self.raws.vendors.iter())
+            .map(|vendor| vendor.name.clone())
+            .collect()
+    }
+
+    /// The uniques that are always found at `depth`
+    pub fn uniques_at(&self, depth: i32) -> Vec<String> {
+        (self.raws.mobs.iter())
+            .filter(|mob| mob.unique.as_ref().and_then(|unique| unique.depth) == Some(depth))
+            .map(|mob| mob.name.clone())
+            .collect()
+    }
+
+    /// Rolls what a monster with the loot table `table` drops
+    pub fn roll_loot(&self, table: &str, rng: &mut RandomNumberGenerator) -> Vec<String> {
+        let loot = match self.raws.loot_tables.get(table) {
+            Some(loot) => loot,
+            None => return Vec::new(),
+        };
+        let mut drops = loot.guaranteed.clone();
+        if !loot.drops.is_empty() && rng.roll_dice(1, 100) <= loot.drop_chance {
+            let table = (loot.drops.iter()).fold(RandomTable::new(), |table, entry| {
+                table.add(&entry.name, entry.weight)
+            });
+            drops.push(table.roll(rng));
+        }
+        drops
+    }
+
+    /// The entries of a spawn table that can appear at `depth`
+    pub fn spawn_table(&self, table: &str, depth: i32) -> RandomTable {
+        let entries = self.raws.spawn_tables.get(table).into_iter().flatten();
+        entries.fold(RandomTable::new(), |random_table, entry| {
+            random_table.add(&entry.name, entry.weight_at(depth))
+        })
+    }
+}
+
+fn invalid(name: &str, reason: &'static str) -> RawsError {
+    RawsError::InvalidTemplate {
+        name: name.to_string(),
+        reason,
+    }
+}
+
+fn validate_renderable(name: &str, renderable: &RenderableTemplate) -> Result<(), RawsError> {
+    // The player is drawn at order 0, on top of everything else
+    if renderable.order < 1 {
+        return Err(invalid(name, "render `order` must be 1 or more"));
+    }
+    Ok(())
+}
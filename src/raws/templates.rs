@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+
+use bracket_lib::prelude::*;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::{
+    components::{EquipmentSlot, MeleeType, MonsterType},
+    identification::ItemKind,
+};
+
+/// Everything in a raws file, as it is written
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Raws {
+    #[serde(default)]
+    pub mobs: Vec<MobTemplate>,
+    #[serde(default)]
+    pub items: Vec<ItemTemplate>,
+    #[serde(default)]
+    pub props: Vec<PropTemplate>,
+    #[serde(default)]
+    pub vendors: Vec<VendorTemplate>,
+    #[serde(default)]
+    pub spawn_tables: BTreeMap<String, Vec<SpawnTableEntry>>,
+    /// What monsters drop when they die, by the name mobs refer to them by
+    #[serde(default)]
+    pub loot_tables: BTreeMap<String, LootTableTemplate>,
+    /// What monsters are called, picked at random
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// Sound files to play in place of the built in ones, by the path of the
+    /// sound they replace
+    #[serde(default)]
+    pub sounds: BTreeMap<String, String>,
+    /// Templates to take out of the game when layered over other raws
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+impl Raws {
+    /// Layers `overlay` on top: templates and spawn table entries replace the
+    /// ones with the same name, and anything new is added. A template replaces
+    /// one of another kind just the same, so a mob can become a prop. Whatever
+    /// the overlay lists in `remove` is taken out first.
+    pub fn merge(&mut self, overlay: Raws) {
+        for name in overlay.remove.iter() {
+            self.remove(name);
+        }
+        let replaced: Vec<String> = (overlay.mobs.iter().map(|mob| &mob.name))
+            .chain(overlay.items.iter().map(|item| &item.name))
+            .chain(overlay.props.iter().map(|prop| &prop.name))
+            .chain(overlay.vendors.iter().map(|vendor| &vendor.name))
+            .cloned()
+            .collect();
+        for name in replaced.iter() {
+            self.remove_template(name);
+        }
+        self.mobs.extend(overlay.mobs);
+        self.items.extend(overlay.items);
+        self.props.extend(overlay.props);
+        self.vendors.extend(overlay.vendors);
+        for (table, entries) in overlay.spawn_tables {
+            let base = self.spawn_tables.entry(table).or_default();
+            merge_by_name(base, entries, |entry| &entry.name);
+        }
+        self.loot_tables.extend(overlay.loot_tables);
+        for name in overlay.names {
+            if !self.names.contains(&name) {
+                self.names.push(name);
+            }
+        }
+        self.sounds.extend(overlay.sounds);
+    }
+
+    /// Takes the template called `name` out, along with every spawn table
+    /// entry, loot entry and piece of mob equipment naming it
+    fn remove(&mut self, name: &str) {
+        self.remove_template(name);
+        for entries in self.spawn_tables.values_mut() {
+            entries.retain(|entry| entry.name != name);
+        }
+        for table in self.loot_tables.values_mut() {
+            table.drops.retain(|entry| entry.name != name);
+            table.guaranteed.retain(|item| item != name);
+        }
+        for mob in self.mobs.iter_mut() {
+            mob.equipment.retain(|item| item != name);
+        }
+    }
+
+    fn remove_template(&mut self, name: &str) {
+        self.mobs.retain(|mob| mob.name != name);
+        self.items.retain(|item| item.name != name);
+        self.props.retain(|prop| prop.name != name);
+        self.vendors.retain(|vendor| vendor.name != name);
+    }
+}
+
+fn merge_by_name<T>(base: &mut Vec<T>, overlay: Vec<T>, name: impl Fn(&T) -> &String) {
+    for new in overlay {
+        match base.iter().position(|old| name(old) == name(&new)) {
+            Some(i) => base[i] = new,
+            None => base.push(new),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RenderableTemplate {
+    #[serde(deserialize_with = "glyph")]
+    pub glyph: FontCharType,
+    #[serde(deserialize_with = "colour")]
+    pub fg: RGB,
+    #[serde(deserialize_with = "colour")]
+    pub bg: RGB,
+    pub order: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LightTemplate {
+    #[serde(deserialize_with = "colour")]
+    pub colour: RGB,
+    pub range: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MobTemplate {
+    pub name: String,
+    pub renderable: RenderableTemplate,
+    /// Stands for it in prefabs and text maps
+    pub map_glyph: Option<char>,
+    pub monster_type: MonsterType,
+    pub vision_range: i32,
+    pub stats: StatsTemplate,
+    pub light: Option<LightTemplate>,
+    /// The loot table rolled when it dies
+    pub loot: Option<String>,
+    /// What it leaves behind when it dies, if anything
+    pub corpse: Option<CorpseTemplate>,
+    /// Items it spawns with, equipped if it can
+    #[serde(default)]
+    pub equipment: Vec<String>,
+    pub unique: Option<UniqueTemplate>,
+}
+
+/// Makes a mob one of a kind, spawned at most once a run
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UniqueTemplate {
+    pub given_name: String,
+    /// The depth it is always found on, if any
+    pub depth: Option<i32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CorpseTemplate {
+    /// How many turns until it has rotted away completely
+    pub rot_turns: i32,
+    #[serde(default)]
+    pub poison: i32,
+    /// How heavy it is to carry, in pounds
+    #[serde(default)]
+    pub weight: f32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StatsTemplate {
+    pub hp: i32,
+    pub defence: i32,
+    pub power: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ItemTemplate {
+    pub name: String,
+    pub renderable: RenderableTemplate,
+    /// Stands for it in prefabs and text maps
+    pub map_glyph: Option<char>,
+    #[serde(default)]
+    pub consumable: bool,
+    /// Piles up with others of its kind in a pack, instead of taking a line each
+    #[serde(default)]
+    pub stackable: bool,
+    #[serde(default)]
+    pub effects: EffectsTemplate,
+    pub equippable: Option<EquippableTemplate>,
+    pub light: Option<LightTemplate>,
+    /// Makes it go by a made up name, and colour, until identified
+    pub unidentified: Option<ItemKind>,
+    /// What a shop sells it for, they buy it back for half
+    #[serde(default)]
+    pub value: i32,
+    /// Makes it a pile of gold, of up to this much per level of depth, which
+    /// goes straight into the wallet when picked up
+    pub gold: Option<i32>,
+    /// How heavy one is, in pounds
+    #[serde(default)]
+    pub weight: f32,
+}
+
+/// What an item does when used, every effect is optional
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct EffectsTemplate {
+    pub healing: Option<i32>,
+    pub damage: Option<i32>,
+    pub ranged: Option<i32>,
+    pub area_of_effect: Option<i32>,
+    pub confusion: Option<i32>,
+    pub fuel: Option<i32>,
+    #[serde(default)]
+    pub food: bool,
+    #[serde(default)]
+    pub magic_mapping: bool,
+    /// Identifies everything else in the user's pack
+    #[serde(default)]
+    pub identify: bool,
+    #[serde(default)]
+    pub remove_curse: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EquippableTemplate {
+    pub slot: EquipmentSlot,
+    #[serde(default)]
+    pub power_bonus: i32,
+    pub melee_type: Option<MeleeType>,
+    #[serde(default)]
+    pub defence_bonus: i32,
+}
+
+/// Anything that stays put and can't be picked up: doors, traps, torches
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PropTemplate {
+    pub name: String,
+    pub renderable: RenderableTemplate,
+    /// Stands for it in prefabs and text maps
+    pub map_glyph: Option<char>,
+    #[serde(default)]
+    pub hidden: bool,
+    /// Set in a wall tile rather than standing on the floor
+    #[serde(default)]
+    pub in_wall: bool,
+    /// A closed door, which blocks movement and sight until opened
+    #[serde(default)]
+    pub door: bool,
+    pub entry_trigger: Option<EntryTriggerTemplate>,
+    pub light: Option<LightTemplate>,
+}
+
+/// Someone to trade with, who stands still and keeps out of fights
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VendorTemplate {
+    pub name: String,
+    pub renderable: RenderableTemplate,
+    /// Stands for it in prefabs and text maps
+    pub map_glyph: Option<char>,
+    /// The spawn table their wares are rolled from, at the depth they're found
+    pub stock: String,
+    /// How many times the table is rolled
+    pub stock_count: i32,
+    /// What they have to pay the player with, for each level down they're found
+    #[serde(default)]
+    pub gold: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EntryTriggerTemplate {
+    pub damage: i32,
+    #[serde(default)]
+    pub single_activation: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTableEntry {
+    pub name: String,
+    pub weight: i32,
+    /// Makes the entry more common the deeper it goes, by one per level
+    #[serde(default)]
+    pub add_depth_to_weight: bool,
+    #[serde(default = "first_depth")]
+    pub min_depth: i32,
+    pub max_depth: Option<i32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LootTableTemplate {
+    /// The percentage chance of rolling on `drops` at all
+    #[serde(default = "always")]
+    pub drop_chance: i32,
+    #[serde(default)]
+    pub drops: Vec<LootEntry>,
+    /// Dropped every time, whatever the roll
+    #[serde(default)]
+    pub guaranteed: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LootEntry {
+    pub name: String,
+    pub weight: i32,
+}
+
+fn always() -> i32 {
+    100
+}
+
+impl SpawnTableEntry {
+    pub fn weight_at(&self, depth: i32) -> i32 {
+        if depth < self.min_depth || self.max_depth.map_or(false, |max| depth > max) {
+            return 0;
+        }
+        if self.add_depth_to_weight {
+            self.weight + depth
+        } else {
+            self.weight
+        }
+    }
+}
+
+fn first_depth() -> i32 {
+    1
+}
+
+/// A glyph is written as the character itself
+fn glyph<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FontCharType, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(to_cp437(c)),
+        _ => Err(de::Error::custom(format!(
+            "glyph `{}` should be a single character",
+            text
+        ))),
+    }
+}
+
+/// A colour is written as `#RRGGBB`
+fn colour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RGB, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    RGB::from_hex(&text).map_err(|e| {
+        de::Error::custom(format!(
+            "colour `{}` should be written as #RRGGBB ({:?})",
+            text, e
+        ))
+    })
+}
@@ -0,0 +1,63 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{mods, SPAWNS_PATH};
+
+/// How often the raws files are checked for changes
+const POLL_MS: f32 = 1000.0;
+
+/// Notices when the raws file, or any mod's, has been saved, by checking
+/// their modification times every so often
+pub struct RawsWatcher {
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    timer: f32,
+}
+
+impl RawsWatcher {
+    pub fn new() -> RawsWatcher {
+        RawsWatcher {
+            modified: Self::modified(),
+            timer: 0.0,
+        }
+    }
+
+    /// The base raws first, then every mod file. Packs being added or
+    /// removed show up as a change too.
+    fn modified() -> Vec<(PathBuf, Option<SystemTime>)> {
+        let mut files = vec![PathBuf::from(SPAWNS_PATH)];
+        files.extend(mods::mod_files());
+        files
+            .into_iter()
+            .map(|path| {
+                let modified = modified_time(&path);
+                (path, modified)
+            })
+            .collect()
+    }
+
+    /// Whether any of the files have changed since they were last checked
+    pub fn poll(&mut self, frame_time_ms: f32) -> bool {
+        self.timer += frame_time_ms;
+        if self.timer < POLL_MS {
+            return false;
+        }
+        self.timer = 0.0;
+
+        let modified = Self::modified();
+        if modified == self.modified {
+            return false;
+        }
+        // A deleted base file is left alone, what's loaded is as good as the built in copy
+        let base_deleted = self.modified[0].1.is_some() && modified[0].1.is_none();
+        self.modified = modified;
+        !base_deleted
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashMap, fmt};
+
+use bracket_lib::random::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+/// The independent random streams derived from the world seed.
+///
+/// Every subsystem rolls from its own stream, so adding a roll in one of them
+/// doesn't shift the results of the others.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum RngStream {
+    MapGen,
+    Spawning,
+    Names,
+    Sound,
+    Discovery,
+    Loot,
+}
+
+impl RngStream {
+    /// Level streams are reseeded from the depth whenever a level is generated,
+    /// so a given depth always comes out the same no matter how the player got there.
+    fn is_per_level(self) -> bool {
+        match self {
+            RngStream::MapGen | RngStream::Spawning | RngStream::Names => true,
+            RngStream::Sound | RngStream::Discovery | RngStream::Loot => false,
+        }
+    }
+}
+
+/// Saved along with the game, streams and all, so a loaded game rolls on
+/// where it left off
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, RandomNumberGenerator>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    pub fn random_seed() -> u64 {
+        RandomNumberGenerator::new().next_u64()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reseed all level streams for generating the level at `depth`
+    pub fn begin_level(&mut self, depth: i32) {
+        for stream in [RngStream::MapGen, RngStream::Spawning, RngStream::Names].iter() {
+            self.streams.insert(
+                *stream,
+                RandomNumberGenerator::seeded(derive_seed(self.seed, *stream, depth)),
+            );
+        }
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut RandomNumberGenerator {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| {
+            let depth = if stream.is_per_level() { 1 } else { 0 };
+            RandomNumberGenerator::seeded(derive_seed(seed, stream, depth))
+        })
+    }
+}
+
+impl fmt::Debug for GameRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GameRng")
+            .field("seed", &self.seed)
+            .field("streams", &self.streams.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn derive_seed(seed: u64, stream: RngStream, depth: i32) -> u64 {
+    splitmix64(seed ^ splitmix64(((stream as u64) << 32) | depth as u32 as u64))
+}
+
+// https://xorshift.di.unimi.it/splitmix64.c
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -17,7 +17,7 @@ use specs::{
 };
 use thiserror::Error;
 
-use crate::components::*;
+use crate::{components::*, dungeon::MasterDungeonMap, gamelog::GameLog, raws::RAWS, rng::GameRng};
 
 #[derive(Error, Debug)]
 pub enum SaveLoadError {
@@ -60,10 +60,18 @@ macro_rules! deserialize_individually {
 pub fn save_game(ecs: &mut World) -> Result<(), SaveLoadError> {
     console::log("Saving game...");
     let mapcopy = ecs.get_mut::<crate::map::Map>().unwrap().clone();
+    let dungeon = (*ecs.fetch::<MasterDungeonMap>()).clone();
+    let rng = (*ecs.fetch::<GameRng>()).clone();
+    let mods = RAWS.lock().unwrap().mods().to_vec();
     let savehelper = ecs
         .create_entity()
         .marked::<SimpleMarker<SerializeMe>>()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper {
+            map: mapcopy,
+            dungeon,
+            rng,
+            mods,
+        })
         .build();
 
     {
@@ -79,13 +87,18 @@ pub fn save_game(ecs: &mut World) -> Result<(), SaveLoadError> {
             serializer,
             data,
             Position,
+            OtherLevelPosition,
             Renderable,
             Viewshed,
             Player,
             Name,
             GivenName,
             Monster,
+            DropsLoot,
+            Unique,
             BlocksTile,
+            BlocksVisibility,
+            Door,
             CombatStats,
             SufferDamage,
             Item,
@@ -103,7 +116,21 @@ pub fn save_game(ecs: &mut World) -> Result<(), SaveLoadError> {
             ParticleLifetime,
             HungerClock,
             ProvidesFood,
+            Corpse,
             MagicMapper,
+            Identifier,
+            Cursed,
+            CurseRemover,
+            Stack,
+            ItemValue,
+            Gold,
+            Wallet,
+            Vendor,
+            Weight,
+            Burden,
+            LightSource,
+            Lantern,
+            ProvidesFuel,
             Animation,
             Hidden,
             EntryTrigger,
@@ -141,13 +168,18 @@ pub fn load_game(ecs: &mut World) -> Result<(), SaveLoadError> {
             de,
             d,
             Position,
+            OtherLevelPosition,
             Renderable,
             Viewshed,
             Player,
             Name,
             GivenName,
             Monster,
+            DropsLoot,
+            Unique,
             BlocksTile,
+            BlocksVisibility,
+            Door,
             CombatStats,
             SufferDamage,
             Item,
@@ -165,7 +197,21 @@ pub fn load_game(ecs: &mut World) -> Result<(), SaveLoadError> {
             ParticleLifetime,
             HungerClock,
             ProvidesFood,
+            Corpse,
             MagicMapper,
+            Identifier,
+            Cursed,
+            CurseRemover,
+            Stack,
+            ItemValue,
+            Gold,
+            Wallet,
+            Vendor,
+            Weight,
+            Burden,
+            LightSource,
+            Lantern,
+            ProvidesFuel,
             Animation,
             Hidden,
             EntryTrigger,
@@ -181,6 +227,7 @@ pub fn load_game(ecs: &mut World) -> Result<(), SaveLoadError> {
     }
 
     let mut deleteme: Option<Entity> = None;
+    let mut player_entity: Option<Entity> = None;
 
     {
         let entities = ecs.entities();
@@ -190,22 +237,53 @@ pub fn load_game(ecs: &mut World) -> Result<(), SaveLoadError> {
         for (e, h) in (&entities, &helper).join() {
             let mut worldmap = ecs.write_resource::<crate::map::Map>();
             *worldmap = h.map.clone();
-            worldmap.tile_content = vec![Vec::new(); crate::map::MAPCOUNT];
+            worldmap.tile_content = vec![Vec::new(); worldmap.tiles.len()];
+            let mut dungeon = ecs.write_resource::<MasterDungeonMap>();
+            *dungeon = h.dungeon.clone();
+            let mut rng = ecs.write_resource::<GameRng>();
+            *rng = h.rng.clone();
+            warn_about_mods(&h.mods, &mut ecs.write_resource::<GameLog>());
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
             let mut ppos = ecs.write_resource::<Point>();
             *ppos = Point::new(pos.x, pos.y);
-            let mut player_resource = ecs.write_resource::<Entity>();
-            *player_resource = e;
+            player_entity = Some(e);
         }
     }
+    // There's no player to replace when loading straight from the menu
+    if let Some(player_entity) = player_entity {
+        ecs.insert(player_entity);
+    }
     ecs.delete_entity(deleteme.unwrap())
         .expect("Unable to delete helper");
 
     Ok(())
 }
 
+/// Spawn tables and templates may have changed since the game was saved with
+/// other mods, so it is loaded anyway but the player is told
+fn warn_about_mods(saved: &[String], gamelog: &mut GameLog) {
+    let active = RAWS.lock().unwrap().mods().to_vec();
+    if saved == active.as_slice() {
+        return;
+    }
+    let describe = |mods: &[String]| {
+        if mods.is_empty() {
+            "no mods".to_string()
+        } else {
+            mods.join(", ")
+        }
+    };
+    let warning = format!(
+        "Warning: this game was saved with {}, and is running with {}.",
+        describe(saved),
+        describe(&active)
+    );
+    console::log(&warning);
+    gamelog.entries.push(warning);
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn does_save_exist() -> bool {
     Path::new("./savegame.json").exists()
@@ -0,0 +1,129 @@
+//! Trading with vendors. A trade happens straight from the shop screen and
+//! doesn't take a turn, one item at a time. Vendors pay for what they buy out
+//! of their own wallet, which what they sell fills back up.
+
+use specs::prelude::*;
+
+use crate::{
+    components::{named, Burden, GivenName, InBackpack, ItemValue, Name, Wallet, Weight},
+    dungeon::MasterDungeonMap,
+    gamelog::GameLog,
+    spawner,
+    systems::stow_item,
+};
+
+/// What a vendor charges for an item
+pub fn buy_price(value: &ItemValue) -> i32 {
+    value.value
+}
+
+/// What a vendor pays for an item, half what they'd sell it for
+pub fn sell_price(value: &ItemValue) -> i32 {
+    value.value / 2
+}
+
+/// The player buys one of `item` from `vendor`'s pack, if they can afford it
+/// and carry it
+pub fn buy(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player = *ecs.fetch::<Entity>();
+    let price = match ecs.read_storage::<ItemValue>().get(item) {
+        Some(value) => buy_price(value),
+        None => return,
+    };
+    let name = item_name(ecs, item);
+    let weight = weight_of_one(ecs, item);
+    if let Some(burden) = ecs.read_storage::<Burden>().get(player) {
+        if !burden.can_carry(weight) {
+            ecs.write_resource::<GameLog>()
+                .entries
+                .push(format!("The {} is too heavy to carry.", name));
+            return;
+        }
+    }
+    {
+        let mut wallets = ecs.write_storage::<Wallet>();
+        let wallet = match wallets.get_mut(player) {
+            Some(wallet) => wallet,
+            None => return,
+        };
+        if wallet.gold < price {
+            ecs.write_resource::<GameLog>()
+                .entries
+                .push(format!("You can't afford the {}.", name));
+            return;
+        }
+        wallet.gold -= price;
+        if let Some(wallet) = wallets.get_mut(vendor) {
+            wallet.gold += price;
+        }
+    }
+    adjust_burden(ecs, player, weight);
+    hand_over(ecs, item, player);
+    ecs.write_resource::<GameLog>()
+        .entries
+        .push(format!("You buy the {} for {} gold.", name, price));
+}
+
+/// The player sells one of `item` from their pack to `vendor`, if the vendor
+/// can afford it
+pub fn sell(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player = *ecs.fetch::<Entity>();
+    let price = match ecs.read_storage::<ItemValue>().get(item) {
+        Some(value) if sell_price(value) > 0 => sell_price(value),
+        _ => return,
+    };
+    let name = item_name(ecs, item);
+    {
+        let mut wallets = ecs.write_storage::<Wallet>();
+        let purse = wallets.get(vendor).map_or(0, |wallet| wallet.gold);
+        if purse < price {
+            let shopkeeper = named(
+                ecs.read_storage::<Name>().get(vendor),
+                ecs.read_storage::<GivenName>().get(vendor),
+            );
+            ecs.write_resource::<GameLog>()
+                .entries
+                .push(format!("{} can't afford the {}.", shopkeeper, name));
+            return;
+        }
+        if let Some(wallet) = wallets.get_mut(vendor) {
+            wallet.gold -= price;
+        }
+        if let Some(wallet) = wallets.get_mut(player) {
+            wallet.gold += price;
+        }
+    }
+    let weight = weight_of_one(ecs, item);
+    adjust_burden(ecs, player, -weight);
+    hand_over(ecs, item, vendor);
+    ecs.write_resource::<GameLog>()
+        .entries
+        .push(format!("You sell the {} for {} gold.", name, price));
+}
+
+/// Moves one of `item`, taken off the top if it's a stack, into `to`'s pack
+fn hand_over(ecs: &mut World, item: Entity, to: Entity) {
+    let piece = spawner::split_stack(ecs, item, 1).unwrap_or(item);
+    ecs.write_storage::<InBackpack>().remove(piece);
+    stow_item(ecs, to, piece);
+}
+
+fn weight_of_one(ecs: &World, item: Entity) -> f32 {
+    ecs.read_storage::<Weight>()
+        .get(item)
+        .map_or(0.0, |weight| weight.weight)
+}
+
+/// Trading doesn't take a turn, so the load is kept up to date here until
+/// the `EncumbranceSystem` next runs
+fn adjust_burden(ecs: &mut World, entity: Entity, weight: f32) {
+    if let Some(burden) = ecs.write_storage::<Burden>().get_mut(entity) {
+        burden.carried += weight;
+    }
+}
+
+fn item_name(ecs: &World, item: Entity) -> String {
+    let names = ecs.read_storage::<Name>();
+    ecs.fetch::<MasterDungeonMap>()
+        .item_name(&names.get(item).unwrap().name)
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use bracket_lib::prelude::*;
 use specs::{
@@ -8,91 +8,36 @@ use specs::{
 
 use crate::{
     components::{
-        AreaOfEffect, BlocksTile, CombatStats, Confusion, Consumable, DefenceBonus, EntryTrigger,
-        Equipable, EquipmentSlot, GivenName, Hidden, HungerClock, HungerState, InflictsDamage,
-        Item, MagicMapper, MeleePowerBonus, MeleeType, Monster, MonsterType, Name, Player,
-        Position, ProvidesFood, ProvidesHealing, Ranged, Renderable, SerializeMe, SingleActivation,
-        Viewshed,
+        AreaOfEffect, BlocksTile, BlocksVisibility, Burden, CombatStats, Confusion, Consumable,
+        Corpse, CurseRemover, Cursed, DefenceBonus, Door, DropsLoot, EntryTrigger, Equipable,
+        EquipmentSlot, Equipped, GivenName, Gold, Hidden, HungerClock, HungerState, Identifier,
+        InBackpack, InflictsDamage, Item, ItemValue, Lantern, LightSource, MagicMapper,
+        MeleePowerBonus, MeleeType, Monster, Name, Player, Position, ProvidesFood, ProvidesFuel,
+        ProvidesHealing, Ranged, Renderable, SerializeMe, SingleActivation, Stack, Unique, Vendor,
+        Viewshed, Wallet, Weight,
     },
-    random_table::RandomTable,
+    dungeon::MasterDungeonMap,
+    map::{Map, TileType},
+    raws::{
+        EffectsTemplate, LightTemplate, RawMaster, RenderableTemplate, Template, VendorTemplate,
+        RAWS,
+    },
+    rect::Rect,
+    rng::{GameRng, RngStream},
+    systems::stow_item,
 };
 
 const MAX_MONSTERS: i32 = 4;
 
-pub fn orc(ecs: &mut World, x: i32, y: i32, given_name: &str) {
-    monster(
-        ecs,
-        x,
-        y,
-        to_cp437('o'),
-        "Orc",
-        given_name,
-        MonsterType::Orc,
-        16,
-        2,
-        6,
-    );
-}
-pub fn goblin(ecs: &mut World, x: i32, y: i32, given_name: &str) {
-    monster(
-        ecs,
-        x,
-        y,
-        to_cp437('g'),
-        "Goblin",
-        given_name,
-        MonsterType::Goblin,
-        16,
-        1,
-        4,
-    );
-}
+/// One room in this many is a shop
+const SHOP_CHANCE: i32 = 12;
 
-fn monster<S: ToString>(
-    ecs: &mut World,
-    x: i32,
-    y: i32,
-    glyph: FontCharType,
-    name: S,
-    given_name: S,
-    monster_type: MonsterType,
-    hp: i32,
-    defence: i32,
-    power: i32,
-) {
-    ecs.create_entity()
-        .marked::<SimpleMarker<SerializeMe>>()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph,
-            fg: RGB::named(RED),
-            bg: RGB::named(BLACK),
-            render_order: 1,
-        })
-        .with(Viewshed {
-            visible_tiles: Vec::new(),
-            range: 8,
-            dirty: true,
-        })
-        .with(Monster {
-            monster_type,
-            seen_player: false,
-        })
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(GivenName {
-            name: given_name.to_string(),
-        })
-        .with(BlocksTile {})
-        .with(CombatStats {
-            max_hp: 16,
-            hp,
-            defence,
-            power,
-        })
-        .build();
-}
+/// How far from where the player arrives a unique is placed, if there's room
+const UNIQUE_MIN_DISTANCE: f32 = 10.0;
+
+/// The percentage chance, per level of depth, of equipment turning up cursed
+const CURSE_CHANCE_PER_DEPTH: i32 = 4;
+const MAX_CURSE_CHANCE: i32 = 40;
 
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
     ecs.create_entity()
@@ -110,12 +55,20 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         .with(Player {})
         .with(Viewshed {
             visible_tiles: Vec::new(),
-            range: 8,
+            range: 16,
             dirty: true,
         })
         .with(Name {
             name: "Player".to_string(),
         })
+        .with(LightSource {
+            colour: RGB::from_f32(1.0, 0.9, 0.6),
+            range: 6,
+        })
+        .with(Lantern {
+            fuel: 1000,
+            max_fuel: 1000,
+        })
         .with(CombatStats {
             max_hp: 30,
             hp: 30,
@@ -126,344 +79,548 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             state: HungerState::WellFed,
             duration: 20,
         })
+        .with(Wallet { gold: 0 })
+        .with(Burden {
+            carried: 0.0,
+            capacity: 0.0,
+        })
         .build()
 }
 
-pub fn rations(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .marked::<SimpleMarker<SerializeMe>>()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437('%'),
-            fg: RGB::named(GREEN),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Rations".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(ProvidesFood {})
-        .build();
-}
+/// Picks monsters and items for a room. The room the player starts in, at
+/// `start`, is never a shop.
+pub fn spawn_room(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+    room: &Rect,
+    start: &Position,
+    map_depth: i32,
+    spawn_list: &mut Vec<(usize, String)>,
+) {
+    let mut possible_targets: Vec<usize> = Vec::new();
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor {
+                possible_targets.push(idx);
+            }
+        }
+    }
 
-pub fn health_potion(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .marked::<SimpleMarker<SerializeMe>>()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437(';'),
-            fg: RGB::named(MAGENTA),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Health Potion".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(ProvidesHealing { heal_amount: 8 })
-        .build();
-}
+    // Now and then a room is a shop, with nobody in it but the shopkeeper
+    let has_start =
+        (room.x1 + 1..=room.x2).contains(&start.x) && (room.y1 + 1..=room.y2).contains(&start.y);
+    if rng.roll_dice(1, SHOP_CHANCE) == 1 && !possible_targets.is_empty() && !has_start {
+        let vendors = RAWS.lock().unwrap().vendors();
+        if !vendors.is_empty() {
+            let vendor = &vendors[(rng.roll_dice(1, vendors.len() as i32) - 1) as usize];
+            let (x, y) = room.center();
+            let centre = map.xy_idx(x, y);
+            let idx = if possible_targets.contains(&centre) {
+                centre
+            } else {
+                possible_targets[0]
+            };
+            spawn_list.push((idx, vendor.clone()));
+            return;
+        }
+    }
 
-pub fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .marked::<SimpleMarker<SerializeMe>>()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437(')'),
-            fg: RGB::named(CYAN),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Magic Missile Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 6 })
-        .build();
+    spawn_region(rng, &possible_targets, map_depth, spawn_list);
 }
 
-pub fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .marked::<SimpleMarker<SerializeMe>>()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437(')'),
-            fg: RGB::named(ORANGE),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Fireball Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 20 })
-        .with(AreaOfEffect { radius: 3 })
-        .build();
-}
+/// Picks monsters and items for an arbitrary set of map tiles
+pub fn spawn_region(
+    rng: &mut RandomNumberGenerator,
+    area: &[usize],
+    map_depth: i32,
+    spawn_list: &mut Vec<(usize, String)>,
+) {
+    let spawn_table = RAWS.lock().unwrap().spawn_table("room", map_depth);
+    // Ordered, so that names are rolled in the same order on every run
+    let mut spawn_points: BTreeMap<usize, String> = BTreeMap::new();
+    let mut areas: Vec<usize> = Vec::from(area);
 
-pub fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .marked::<SimpleMarker<SerializeMe>>()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437(')'),
-            fg: RGB::named(PINK),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Confusion Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(Confusion { turns: 4 })
-        .build();
+    let num_spawns = i32::min(
+        areas.len() as i32,
+        rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3,
+    );
+
+    for _ in 0..num_spawns {
+        let array_index = if areas.len() == 1 {
+            0usize
+        } else {
+            (rng.roll_dice(1, areas.len() as i32) - 1) as usize
+        };
+        let map_idx = areas[array_index];
+        spawn_points.insert(map_idx, spawn_table.roll(rng));
+        areas.remove(array_index);
+    }
+
+    spawn_list.extend(spawn_points.into_iter());
 }
 
-pub fn dagger(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437('/'),
-            fg: RGB::named(CYAN),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Dagger".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus {
-            power: 2,
-            melee_type: MeleeType::Slash,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+/// Fills a treasure room with a few items, and nothing to guard them
+pub fn spawn_treasure(
+    rng: &mut RandomNumberGenerator,
+    area: &[usize],
+    map_depth: i32,
+    spawn_list: &mut Vec<(usize, String)>,
+) {
+    let treasure_table = RAWS.lock().unwrap().spawn_table("treasure", map_depth);
+    let mut areas: Vec<usize> = Vec::from(area);
+    let num_items = i32::min(areas.len() as i32, rng.roll_dice(1, 2) + 1);
+    for _ in 0..num_items {
+        let array_index = (rng.roll_dice(1, areas.len() as i32) - 1) as usize;
+        spawn_list.push((areas.remove(array_index), treasure_table.roll(rng)));
+    }
 }
 
-pub fn longsword(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437('/'),
-            fg: RGB::named(YELLOW),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Longsword".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus {
-            power: 4,
-            melee_type: MeleeType::Slash,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+/// Spawns whatever the raws call `key`, be it a monster, an item or a prop.
+/// Returns `None` if there is no such thing, or it's a unique already spawned.
+pub fn spawn_named(ecs: &mut World, key: &str, position: Position) -> Option<Entity> {
+    let raws = RAWS.lock().unwrap();
+    build_named(ecs, &raws, key, Some(position))
 }
 
-pub fn shield(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437('('),
-            fg: RGB::named(CYAN),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
+fn build_named(
+    ecs: &mut World,
+    raws: &RawMaster,
+    key: &str,
+    position: Option<Position>,
+) -> Option<Entity> {
+    let mut equipment: &[String] = &[];
+    let mut shop = None;
+    let mut builder = match raws.get(key)? {
+        Template::Mob(mob) => {
+            let given_name = match &mob.unique {
+                Some(unique) => {
+                    let mut dungeon = ecs.write_resource::<MasterDungeonMap>();
+                    if dungeon.has_spawned(&mob.name) {
+                        return None;
+                    }
+                    dungeon.mark_spawned(&mob.name);
+                    unique.given_name.clone()
+                }
+                None => {
+                    let mut rng = ecs.write_resource::<GameRng>();
+                    raws.name_table().roll(rng.stream(RngStream::Names))
+                }
+            };
+            equipment = &mob.equipment;
+            let mut builder = ecs
+                .create_entity()
+                .with(renderable(&mob.renderable))
+                .with(Viewshed {
+                    visible_tiles: Vec::new(),
+                    range: mob.vision_range,
+                    dirty: true,
+                })
+                .with(Monster {
+                    monster_type: mob.monster_type,
+                    seen_player: false,
+                })
+                .with(GivenName { name: given_name })
+                .with(BlocksTile {})
+                .with(CombatStats {
+                    max_hp: mob.stats.hp,
+                    hp: mob.stats.hp,
+                    defence: mob.stats.defence,
+                    power: mob.stats.power,
+                });
+            if let Some(table) = &mob.loot {
+                builder = builder.with(DropsLoot {
+                    table: table.clone(),
+                });
+            }
+            if mob.unique.is_some() {
+                builder = builder.with(Unique { spotted: false });
+            }
+            with_light(builder, &mob.light)
+        }
+        Template::Item(item) => {
+            let mut render = renderable(&item.renderable);
+            if let Some(kind) = item.unidentified {
+                let mut dungeon = ecs.write_resource::<MasterDungeonMap>();
+                let mut rng = ecs.write_resource::<GameRng>();
+                let disguise = dungeon.disguise(&item.name, kind, rng.stream(RngStream::Names));
+                render.fg = disguise.colour;
+            }
+            let mut builder = ecs.create_entity().with(render).with(Item {});
+            if item.consumable {
+                builder = builder.with(Consumable {});
+            }
+            if item.stackable {
+                builder = builder.with(Stack { count: 1 });
+            }
+            if item.value > 0 {
+                builder = builder.with(ItemValue { value: item.value });
+            }
+            if let Some(gold) = item.gold {
+                builder = builder.with(Gold { amount: gold });
+            }
+            if item.weight > 0.0 {
+                builder = builder.with(Weight {
+                    weight: item.weight,
+                });
+            }
+            builder = with_effects(builder, &item.effects);
+            if let Some(equippable) = &item.equippable {
+                builder = builder.with(Equipable {
+                    slot: equippable.slot,
+                });
+                if equippable.power_bonus != 0 {
+                    builder = builder.with(MeleePowerBonus {
+                        power: equippable.power_bonus,
+                        melee_type: equippable.melee_type.unwrap_or(MeleeType::Blunt),
+                    });
+                }
+                if equippable.defence_bonus != 0 {
+                    builder = builder.with(DefenceBonus {
+                        defence: equippable.defence_bonus,
+                    });
+                }
+            }
+            with_light(builder, &item.light)
+        }
+        Template::Prop(prop) => {
+            let mut builder = ecs.create_entity().with(renderable(&prop.renderable));
+            if prop.hidden {
+                builder = builder.with(Hidden {});
+            }
+            if prop.door {
+                builder = builder
+                    .with(Door { open: false })
+                    .with(BlocksTile {})
+                    .with(BlocksVisibility {});
+            }
+            if let Some(trigger) = &prop.entry_trigger {
+                builder = builder.with(EntryTrigger {}).with(InflictsDamage {
+                    damage: trigger.damage,
+                });
+                if trigger.single_activation {
+                    builder = builder.with(SingleActivation {});
+                }
+            }
+            with_light(builder, &prop.light)
+        }
+        Template::Vendor(vendor) => {
+            let given_name = {
+                let mut rng = ecs.write_resource::<GameRng>();
+                raws.name_table().roll(rng.stream(RngStream::Names))
+            };
+            shop = Some(vendor);
+            let depth = ecs.fetch::<Map>().depth;
+            ecs.create_entity()
+                .with(renderable(&vendor.renderable))
+                .with(GivenName { name: given_name })
+                .with(BlocksTile {})
+                .with(Vendor {})
+                .with(Wallet {
+                    gold: vendor.gold * depth,
+                })
+        }
+    };
+    if let Some(position) = position {
+        builder = builder.with(position);
+    }
+    let entity = builder
         .with(Name {
-            name: "Shield".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Shield,
+            name: key.to_string(),
         })
-        .with(DefenceBonus { defence: 1 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    equip(ecs, raws, entity, equipment);
+    if let Some(vendor) = shop {
+        stock_shop(ecs, raws, entity, vendor);
+    }
+    if ecs.read_storage::<Gold>().get(entity).is_some() {
+        roll_gold(ecs, entity);
+    }
+    roll_curse(ecs, entity);
+    Some(entity)
 }
 
-pub fn tower_shield(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437('('),
-            fg: RGB::named(YELLOW),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Tower Shield".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Shield,
-        })
-        .with(DefenceBonus { defence: 3 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+/// Fills a vendor's pack with wares for the depth they're found at
+fn stock_shop(ecs: &mut World, raws: &RawMaster, vendor: Entity, template: &VendorTemplate) {
+    let depth = ecs.fetch::<Map>().depth;
+    let table = raws.spawn_table(&template.stock, depth);
+    for _ in 0..template.stock_count {
+        let name = {
+            let mut rng = ecs.write_resource::<GameRng>();
+            table.roll(rng.stream(RngStream::Spawning))
+        };
+        if let Some(item) = build_named(ecs, raws, &name, None) {
+            stow_item(ecs, vendor, item);
+        }
+    }
 }
 
-pub fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437(')'),
-            fg: RGB::named(CYAN3),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Scroll of Magic Mapping".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(MagicMapper {})
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+/// Turns the most a gold pile can hold per level into how much this one
+/// does, so piles get richer the deeper they are
+fn roll_gold(ecs: &mut World, pile: Entity) {
+    let depth = ecs.fetch::<Map>().depth;
+    let mut rng = ecs.write_resource::<GameRng>();
+    if let Some(gold) = ecs.write_storage::<Gold>().get_mut(pile) {
+        gold.amount = rng
+            .stream(RngStream::Spawning)
+            .roll_dice(depth, gold.amount);
+    }
 }
 
-pub fn bear_trap(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: to_cp437('^'),
-            fg: RGB::named(RED),
-            bg: RGB::named(BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Bear Trap".to_string(),
-        })
-        .with(Hidden {})
-        .with(EntryTrigger {})
-        .with(SingleActivation {})
-        .with(InflictsDamage { damage: 6 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+/// Takes `count` items off a stack into a new stack of their own, which isn't
+/// anywhere yet. Returns `None` unless there are more than `count` to take.
+pub fn split_stack(ecs: &mut World, stack: Entity, count: i32) -> Option<Entity> {
+    let key = {
+        let stacks = ecs.read_storage::<Stack>();
+        if stacks.get(stack)?.count <= count {
+            return None;
+        }
+        ecs.read_storage::<Name>().get(stack)?.name.clone()
+    };
+    let split = {
+        let raws = RAWS.lock().unwrap();
+        build_named(ecs, &raws, &key, None)?
+    };
+    let mut stacks = ecs.write_storage::<Stack>();
+    stacks
+        .insert(split, Stack { count })
+        .expect("Unable to insert stack");
+    if let Some(original) = stacks.get_mut(stack) {
+        original.count -= count;
+    }
+    Some(split)
 }
 
-fn room_table(map_depth: i32) -> RandomTable {
-    RandomTable::new()
-        .add("Goblin", 10)
-        .add("Orc", 1 + map_depth)
-        .add("Health Potion", 7)
-        .add("Fireball Scroll", 2 + map_depth)
-        .add("Confusion Scroll", 2 + map_depth)
-        .add("Magic Missile Scroll", 4)
-        .add("Dagger", 3)
-        .add("Shield", 3)
-        .add("Longsword", map_depth - 1)
-        .add("Tower Shield", map_depth - 1)
-        .add("Rations", 10)
-        .add("Magic Mapping Scroll", 2)
-        .add("Bear Trap", 3)
+/// Hands a mob the items it spawns with, wearing the first for each slot and
+/// carrying the rest
+fn equip(ecs: &mut World, raws: &RawMaster, owner: Entity, items: &[String]) {
+    let mut worn: Vec<EquipmentSlot> = Vec::new();
+    for name in items {
+        let item = match build_named(ecs, raws, name, None) {
+            Some(item) => item,
+            None => continue,
+        };
+        let slot = ecs.read_storage::<Equipable>().get(item).map(|e| e.slot);
+        match slot {
+            Some(slot) if !worn.contains(&slot) => {
+                worn.push(slot);
+                ecs.write_storage::<Equipped>()
+                    .insert(item, Equipped { owner, slot })
+                    .expect("Unable to equip item");
+            }
+            _ => {
+                ecs.write_storage::<InBackpack>()
+                    .insert(item, InBackpack { owner })
+                    .expect("Unable to insert item into backpack");
+            }
+        }
+    }
 }
 
-fn name_table() -> RandomTable {
-    RandomTable::new()
-        .add("Thomas", 1)
-        .add("George", 1)
-        .add("Jacques", 1)
-        .add("Alex", 1)
-        .add("Catriona", 1)
-        .add("Lisa", 1)
-        .add("Torbjörn", 1)
-        .add("Steve", 1)
-        .add("Karolina", 1)
-        .add("Jenny", 1)
-        .add("Chrisp", 1)
-        .add("Maria", 1)
-        .add("Martin", 1)
-        .add("Krisi", 1)
-        .add("Ines", 1)
-        .add("Marko", 1)
-        .add("Miles", 1)
-        .add("David", 1)
-        .add("Tom", 1)
-        .add("Tinks", 1)
-        .add("Zemì", 1)
-        .add("Remi", 1)
-        .add("Mo", 1)
-        .add("Xinga", 1)
-        .add("Lulu", 1)
+/// Sometimes curses a piece of equipment, which is more likely the deeper the
+/// level it turns up on. A cursed item's bonuses turn into penalties.
+fn roll_curse(ecs: &mut World, item: Entity) {
+    if ecs.read_storage::<Equipable>().get(item).is_none() {
+        return;
+    }
+    let depth = ecs.fetch::<Map>().depth;
+    let chance = i32::min(depth * CURSE_CHANCE_PER_DEPTH, MAX_CURSE_CHANCE);
+    let roll = ecs
+        .write_resource::<GameRng>()
+        .stream(RngStream::Spawning)
+        .roll_dice(1, 100);
+    if roll > chance {
+        return;
+    }
+
+    if let Some(bonus) = ecs.write_storage::<MeleePowerBonus>().get_mut(item) {
+        bonus.power = -bonus.power.abs();
+    }
+    if let Some(bonus) = ecs.write_storage::<DefenceBonus>().get_mut(item) {
+        bonus.defence = -bonus.defence.abs();
+    }
+    ecs.write_storage::<Cursed>()
+        .insert(item, Cursed { known: false })
+        .expect("Unable to curse item");
 }
 
-pub fn spawn_room(ecs: &mut World, room: &crate::rect::Rect, map_depth: i32) {
-    use crate::map::MAPWIDTH;
+/// Places the uniques that are always found at `depth`, on a free floor tile
+/// well away from where the player arrives
+pub fn spawn_uniques(ecs: &mut World, depth: i32, start: &Position) {
+    let uniques = RAWS.lock().unwrap().uniques_at(depth);
+    for unique in uniques {
+        if ecs.fetch::<MasterDungeonMap>().has_spawned(&unique) {
+            continue;
+        }
+        let position = {
+            let map = ecs.fetch::<Map>();
+            let positions = ecs.read_storage::<Position>();
+            let blockers = ecs.read_storage::<BlocksTile>();
+            let taken: Vec<usize> = (&positions, &blockers)
+                .join()
+                .map(|(pos, _)| map.xy_idx(pos.x, pos.y))
+                .collect();
+            let origin = Point::new(start.x, start.y);
+            let free: Vec<usize> = (map.tiles.iter().enumerate())
+                .filter(|(idx, tile)| **tile == TileType::Floor && !taken.contains(idx))
+                .map(|(idx, _)| idx)
+                .collect();
+            let far: Vec<usize> = (free.iter())
+                .filter(|idx| {
+                    let point = Point::new(**idx as i32 % map.width, **idx as i32 / map.width);
+                    DistanceAlg::Pythagoras.distance2d(origin, point) >= UNIQUE_MIN_DISTANCE
+                })
+                .copied()
+                .collect();
+            let candidates = if far.is_empty() { free } else { far };
+            if candidates.is_empty() {
+                continue;
+            }
+            let mut rng = ecs.write_resource::<GameRng>();
+            let roll = rng
+                .stream(RngStream::Spawning)
+                .roll_dice(1, candidates.len() as i32);
+            let idx = candidates[roll as usize - 1] as i32;
+            Position {
+                x: idx % map.width,
+                y: idx / map.width,
+            }
+        };
+        spawn_named(ecs, &unique, position);
+    }
+}
 
-    let spawn_table = room_table(map_depth);
-    let name_table = name_table();
-    let mut spawn_points: HashMap<usize, String> = HashMap::new();
+fn renderable(template: &RenderableTemplate) -> Renderable {
+    Renderable {
+        glyph: template.glyph,
+        fg: template.fg,
+        bg: template.bg,
+        render_order: template.order,
+    }
+}
 
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_spawns = rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3;
+fn with_light<'a>(builder: EntityBuilder<'a>, light: &Option<LightTemplate>) -> EntityBuilder<'a> {
+    match light {
+        Some(light) => builder.with(LightSource {
+            colour: light.colour,
+            range: light.range,
+        }),
+        None => builder,
+    }
+}
 
-        for _ in 0..num_spawns {
-            let mut added = false;
-            let mut tries = 0;
-            while !added && tries < 20 {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                // same as Map.xy_idx()
-                let idx = (y * MAPWIDTH) + x;
-                if !spawn_points.contains_key(&idx) {
-                    spawn_points.insert(idx, spawn_table.roll(&mut rng));
-                    added = true;
-                } else {
-                    tries += 1;
-                }
-            }
-        }
+fn with_effects<'a>(
+    mut builder: EntityBuilder<'a>,
+    effects: &EffectsTemplate,
+) -> EntityBuilder<'a> {
+    if let Some(heal_amount) = effects.healing {
+        builder = builder.with(ProvidesHealing { heal_amount });
+    }
+    if let Some(range) = effects.ranged {
+        builder = builder.with(Ranged { range });
+    }
+    if let Some(damage) = effects.damage {
+        builder = builder.with(InflictsDamage { damage });
     }
+    if let Some(radius) = effects.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(turns) = effects.confusion {
+        builder = builder.with(Confusion { turns });
+    }
+    if let Some(amount) = effects.fuel {
+        builder = builder.with(ProvidesFuel { amount });
+    }
+    if effects.food {
+        builder = builder.with(ProvidesFood {});
+    }
+    if effects.magic_mapping {
+        builder = builder.with(MagicMapper {});
+    }
+    if effects.identify {
+        builder = builder.with(Identifier {});
+    }
+    if effects.remove_curse {
+        builder = builder.with(CurseRemover {});
+    }
+    builder
+}
 
-    for spawn in spawn_points.iter() {
-        let x = (*spawn.0 % MAPWIDTH) as i32;
-        let y = (*spawn.0 / MAPWIDTH) as i32;
+/// Opens a freshly spawned door, which starts out closed
+pub fn open_door(ecs: &mut World, door: Entity) {
+    set_door_open(
+        door,
+        true,
+        &mut ecs.write_storage::<Door>(),
+        &mut ecs.write_storage::<BlocksTile>(),
+        &mut ecs.write_storage::<BlocksVisibility>(),
+        &mut ecs.write_storage::<Renderable>(),
+    );
+}
 
-        // TODO the name rng scope thingy is pretty ugly
-        match spawn.1.as_ref() {
-            "Goblin" => {
-                let name = {
-                    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-                    name_table.roll(&mut rng)
-                };
-                goblin(ecs, x, y, name.as_str())
-            }
-            "Orc" => {
-                let name = {
-                    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-                    name_table.roll(&mut rng)
-                };
-                orc(ecs, x, y, name.as_str())
-            }
-            "Health Potion" => health_potion(ecs, x, y),
-            "Fireball Scroll" => fireball_scroll(ecs, x, y),
-            "Confusion Scroll" => confusion_scroll(ecs, x, y),
-            "Magic Missile Scroll" => magic_missile_scroll(ecs, x, y),
-            "Dagger" => dagger(ecs, x, y),
-            "Shield" => shield(ecs, x, y),
-            "Longsword" => longsword(ecs, x, y),
-            "Tower Shield" => tower_shield(ecs, x, y),
-            "Rations" => rations(ecs, x, y),
-            "Magic Mapping Scroll" => magic_mapping_scroll(ecs, x, y),
-            "Bear Trap" => bear_trap(ecs, x, y),
-            _ => {}
-        }
+/// Opens or closes a door, which only blocks movement and sight while closed.
+/// Does nothing if `door` isn't one.
+pub fn set_door_open(
+    door: Entity,
+    open: bool,
+    doors: &mut WriteStorage<Door>,
+    blocks_tile: &mut WriteStorage<BlocksTile>,
+    blocks_visibility: &mut WriteStorage<BlocksVisibility>,
+    renderables: &mut WriteStorage<Renderable>,
+) {
+    match doors.get_mut(door) {
+        Some(door_state) => door_state.open = open,
+        None => return,
+    }
+    if open {
+        blocks_tile.remove(door);
+        blocks_visibility.remove(door);
+    } else {
+        blocks_tile
+            .insert(door, BlocksTile {})
+            .expect("Unable to insert blocker");
+        blocks_visibility
+            .insert(door, BlocksVisibility {})
+            .expect("Unable to insert blocker");
+    }
+    if let Some(renderable) = renderables.get_mut(door) {
+        renderable.glyph = to_cp437(if open { '/' } else { '+' });
     }
 }
+
+/// Leaves the corpse of the monster the raws call `key` at `position`, if
+/// that kind of monster leaves one
+pub fn spawn_corpse(ecs: &mut World, key: &str, position: Position) -> Option<Entity> {
+    let raws = RAWS.lock().unwrap();
+    let mob = match raws.get(key)? {
+        Template::Mob(mob) => mob,
+        _ => return None,
+    };
+    let corpse = mob.corpse.as_ref()?;
+    let entity = ecs
+        .create_entity()
+        .with(position)
+        .with(Renderable {
+            glyph: to_cp437('%'),
+            fg: mob.renderable.fg,
+            bg: mob.renderable.bg,
+            render_order: 2,
+        })
+        .with(Name {
+            name: format!("{} corpse", key),
+        })
+        .with(Item {})
+        .with(Consumable {})
+        .with(ProvidesFood {})
+        .with(Corpse {
+            turns_left: corpse.rot_turns,
+            poison: corpse.poison,
+        })
+        .with(Weight {
+            weight: corpse.weight,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    Some(entity)
+}
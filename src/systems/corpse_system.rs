@@ -0,0 +1,68 @@
+use bracket_lib::prelude::RGB;
+use specs::prelude::*;
+
+use crate::{
+    components::{Corpse, InBackpack, Name, Renderable},
+    gamelog::GameLog,
+    RunState,
+};
+
+/// Rots corpses away, wherever they are
+pub struct CorpseSystem {}
+
+impl<'a> System<'a> for CorpseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, Corpse>,
+        WriteStorage<'a, Name>,
+        WriteStorage<'a, Renderable>,
+        ReadStorage<'a, InBackpack>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            runstate,
+            mut gamelog,
+            mut corpses,
+            mut names,
+            mut renderables,
+            backpack,
+        ) = data;
+
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for (entity, corpse, name) in (&entities, &mut corpses, &mut names).join() {
+            corpse.turns_left -= 1;
+            let carried = backpack
+                .get(entity)
+                .map_or(false, |in_backpack| in_backpack.owner == *player_entity);
+
+            if corpse.turns_left == Corpse::ROTTEN_TURNS {
+                if carried {
+                    gamelog
+                        .entries
+                        .push(format!("The {} in your pack starts to smell.", name.name));
+                }
+                name.name = format!("rotting {}", name.name);
+                if let Some(renderable) = renderables.get_mut(entity) {
+                    renderable.fg = renderable.fg.lerp(RGB::from_f32(0.4, 0.45, 0.1), 0.6);
+                }
+            } else if corpse.turns_left <= 0 {
+                if carried {
+                    gamelog
+                        .entries
+                        .push(format!("The {} in your pack rots away.", name.name));
+                }
+                entities.delete(entity).expect("Unable to delete corpse");
+            }
+        }
+    }
+}
@@ -10,14 +10,23 @@ use specs::prelude::*;
 use crate::{
     audio::{DesireMusic, Music, SoundResource},
     components::Position,
+    dungeon::MasterDungeonMap,
     gamelog::GameLog,
-    map::Map,
+    map::{DecalKind, Map},
+    rng::{GameRng, RngStream},
 };
 use crate::{
-    components::{named, CombatStats, GivenName, Name, Player, SufferDamage},
-    RunState,
+    components::{
+        named, CombatStats, DropsLoot, Equipped, GivenName, Gold, InBackpack, Name, Player,
+        SufferDamage, Unique,
+    },
+    raws::{Template, RAWS},
+    spawner, RunState,
 };
 
+/// A gold pile at least this big is worth mentioning when a monster drops it
+const NOTABLE_GOLD: i32 = 50;
+
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
@@ -37,7 +46,7 @@ impl<'a> System<'a> for DamageSystem {
             let pos = positions.get(entity);
             if let Some(pos) = pos {
                 let idx = map.xy_idx(pos.x, pos.y);
-                map.bloodstains.insert(idx);
+                map.add_decal(idx, DecalKind::Blood);
             }
         }
 
@@ -46,15 +55,16 @@ impl<'a> System<'a> for DamageSystem {
 }
 
 pub fn delete_the_dead(ecs: &mut World) {
-    let mut dead: Vec<Entity> = Vec::new();
+    let mut dead: Vec<(Entity, String)> = Vec::new();
 
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let players = ecs.read_storage::<Player>();
         let given_names = ecs.read_storage::<GivenName>();
+        let uniques = ecs.read_storage::<Unique>();
         let names = ecs.read_storage::<Name>();
         let mut sounds = ecs.write_resource::<SoundResource>();
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let mut rng = ecs.write_resource::<GameRng>();
         let entities = ecs.entities();
         let mut log = ecs.write_resource::<GameLog>();
         for (entity, stats) in (&entities, &combat_stats).join() {
@@ -63,10 +73,18 @@ pub fn delete_the_dead(ecs: &mut World) {
                 match player {
                     None => {
                         let title = named(names.get(entity), given_names.get(entity));
-                        log.entries.push(format!("{} is dead", title));
-                        dead.push(entity);
-                        match sounds.play_sound(death_sound(&mut rng), InstanceSettings::default())
-                        {
+                        if uniques.get(entity).is_some() {
+                            log.entries.push(format!("{} has been slain!", title));
+                            let mut desire_music = ecs.write_resource::<DesireMusic>();
+                            desire_music.music = Some(Music::Abyss);
+                        } else {
+                            log.entries.push(format!("{} is dead", title));
+                        }
+                        dead.push((entity, title));
+                        match sounds.play_sound(
+                            death_sound(rng.stream(RngStream::Sound)),
+                            InstanceSettings::default(),
+                        ) {
                             Ok(_) => {}
                             Err(e) => console::log(format!("Unable to play sound: {}", e)),
                         }
@@ -82,11 +100,109 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
-    for victim in dead {
+    for (victim, title) in dead {
+        drop_loot(ecs, victim, &title);
+        leave_corpse(ecs, victim);
         ecs.delete_entity(victim).expect("Unable to delete victim");
     }
 }
 
+fn leave_corpse(ecs: &mut World, victim: Entity) {
+    let (key, pos) = {
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        match (names.get(victim), positions.get(victim)) {
+            (Some(name), Some(pos)) => (name.name.clone(), pos.clone()),
+            _ => return,
+        }
+    };
+    spawner::spawn_corpse(ecs, &key, pos);
+}
+
+/// Leaves what a slain monster was carrying where it fell, along with
+/// whatever its loot table comes up with
+fn drop_loot(ecs: &mut World, victim: Entity, title: &str) {
+    let pos = match ecs.read_storage::<Position>().get(victim) {
+        Some(pos) => pos.clone(),
+        None => return,
+    };
+    let mut dropped: Vec<Entity> = Vec::new();
+
+    {
+        let entities = ecs.entities();
+        let mut positions = ecs.write_storage::<Position>();
+        let mut backpack = ecs.write_storage::<InBackpack>();
+        let mut equipped = ecs.write_storage::<Equipped>();
+        let carried: Vec<Entity> = (&entities, backpack.maybe(), equipped.maybe())
+            .join()
+            .filter(|(_, in_backpack, equipped)| {
+                in_backpack.map_or(false, |in_backpack| in_backpack.owner == victim)
+                    || equipped.map_or(false, |equipped| equipped.owner == victim)
+            })
+            .map(|(item, _, _)| item)
+            .collect();
+        for item in carried {
+            backpack.remove(item);
+            equipped.remove(item);
+            positions
+                .insert(item, pos.clone())
+                .expect("Unable to insert position");
+            dropped.push(item);
+        }
+    }
+
+    let loot = {
+        let loot_tables = ecs.read_storage::<DropsLoot>();
+        let mut rng = ecs.write_resource::<GameRng>();
+        match loot_tables.get(victim) {
+            Some(loot) => RAWS
+                .lock()
+                .unwrap()
+                .roll_loot(&loot.table, rng.stream(RngStream::Loot)),
+            None => Vec::new(),
+        }
+    };
+    for name in loot {
+        if let Some(item) = spawner::spawn_named(ecs, &name, pos.clone()) {
+            dropped.push(item);
+        }
+    }
+
+    // Only mention what's worth going over to pick up; everything a unique
+    // drops is
+    let notable: Vec<String> = {
+        let from_unique = ecs.read_storage::<Unique>().get(victim).is_some();
+        let names = ecs.read_storage::<Name>();
+        let gold = ecs.read_storage::<Gold>();
+        let dungeon = ecs.fetch::<MasterDungeonMap>();
+        let raws = RAWS.lock().unwrap();
+        (dropped.iter())
+            .filter_map(|item| {
+                let name = &names.get(*item)?.name;
+                let notable = from_unique
+                    || gold.get(*item).map_or(false, |g| g.amount >= NOTABLE_GOLD)
+                    || match raws.get(name) {
+                        // Equipment, and anything magic enough to go unidentified
+                        Some(Template::Item(template)) => {
+                            template.equippable.is_some() || template.unidentified.is_some()
+                        }
+                        _ => false,
+                    };
+                if notable {
+                    Some(dungeon.item_name(name))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+    if !notable.is_empty() {
+        let mut log = ecs.write_resource::<GameLog>();
+        log.entries
+            .push(format!("{} drops {}.", title, notable.join(", ")));
+    }
+}
+
 fn death_sound(rng: &mut RandomNumberGenerator) -> &str {
     let roll = rng.roll_dice(1, 2);
     match roll {
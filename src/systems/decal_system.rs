@@ -0,0 +1,102 @@
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+use crate::{
+    components::{named, EntityMoved, GivenName, Name, Position, Viewshed},
+    gamelog::GameLog,
+    map::{DecalKind, Map, TileType},
+    RunState,
+};
+
+/// Fades decals, leaves tracks behind whoever walks through them and makes
+/// slippery ones slippery
+pub struct DecalSystem {}
+
+impl<'a> System<'a> for DecalSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, Point>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, GivenName>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut map,
+            player_entity,
+            mut player_pos,
+            runstate,
+            mut gamelog,
+            entities,
+            entity_moved,
+            mut positions,
+            mut viewsheds,
+            names,
+            given_names,
+        ) = data;
+
+        if *runstate == RunState::MonsterTurn {
+            map.age_decals();
+        }
+
+        for (entity, moved, pos, viewshed) in
+            (&entities, &entity_moved, &mut positions, &mut viewsheds).join()
+        {
+            let from_idx = map.xy_idx(moved.from.x, moved.from.y);
+            let idx = map.xy_idx(pos.x, pos.y);
+
+            match map.tiles[from_idx] {
+                TileType::ShallowWater | TileType::DeepWater => {
+                    map.add_decal(idx, DecalKind::Water)
+                }
+                _ => {
+                    if map
+                        .decal_kind(from_idx)
+                        .map_or(false, |k| k.leaves_tracks())
+                    {
+                        map.add_decal(idx, DecalKind::Footprints);
+                    }
+                }
+            }
+
+            if !map.decal_kind(idx).map_or(false, |k| k.is_slippery()) {
+                continue;
+            }
+
+            // Keep going the same way for one more tile, if there is room
+            let slide_x = pos.x * 2 - moved.from.x;
+            let slide_y = pos.y * 2 - moved.from.y;
+            if slide_x < 1 || slide_x > map.width - 2 || slide_y < 1 || slide_y > map.height - 2 {
+                continue;
+            }
+            let slide_idx = map.xy_idx(slide_x, slide_y);
+            if map.blocked[slide_idx] || !map.tiles[slide_idx].holds_decals() {
+                continue;
+            }
+
+            map.blocked[idx] = false;
+            map.blocked[slide_idx] = true;
+            pos.x = slide_x;
+            pos.y = slide_y;
+            viewshed.dirty = true;
+            if entity == *player_entity {
+                player_pos.x = slide_x;
+                player_pos.y = slide_y;
+                gamelog.entries.push("You slip on the slime!".to_string());
+            } else if map.visible_tiles[idx] {
+                gamelog.entries.push(format!(
+                    "{} slips on the slime.",
+                    named(names.get(entity), given_names.get(entity))
+                ));
+            }
+        }
+    }
+}
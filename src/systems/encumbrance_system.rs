@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use specs::prelude::*;
+
+use crate::{
+    components::{stack_weight, Burden, CombatStats, Equipped, InBackpack, Stack, Weight},
+    gamelog::GameLog,
+};
+
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, Burden>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Stack>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            mut gamelog,
+            mut burdens,
+            combat_stats,
+            backpack,
+            equipped,
+            weights,
+            stacks,
+        ) = data;
+
+        // What's worn counts as much as what's in the pack
+        let mut carried: HashMap<Entity, f32> = HashMap::new();
+        for (item, pack) in (&entities, &backpack).join() {
+            *carried.entry(pack.owner).or_insert(0.0) +=
+                stack_weight(weights.get(item), stacks.get(item));
+        }
+        for (item, worn) in (&entities, &equipped).join() {
+            *carried.entry(worn.owner).or_insert(0.0) +=
+                stack_weight(weights.get(item), stacks.get(item));
+        }
+
+        for (entity, burden, stats) in (&entities, &mut burdens, &combat_stats).join() {
+            let was_overburdened = burden.is_overburdened();
+            burden.carried = carried.get(&entity).copied().unwrap_or(0.0);
+            burden.capacity = Burden::capacity_for(stats);
+            if entity == *player_entity && burden.is_overburdened() != was_overburdened {
+                if burden.is_overburdened() {
+                    gamelog
+                        .entries
+                        .push("You are overburdened, and tire quickly.".to_string());
+                } else {
+                    gamelog
+                        .entries
+                        .push("You are no longer overburdened.".to_string());
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use specs::prelude::*;
 
 use crate::{
-    components::{HungerClock, HungerState, SufferDamage},
+    components::{Burden, HungerClock, HungerState, SufferDamage},
     gamelog::GameLog,
     RunState,
 };
@@ -17,11 +17,19 @@ impl<'a> System<'a> for HungerSystem {
         ReadExpect<'a, RunState>,
         WriteStorage<'a, SufferDamage>,
         WriteExpect<'a, GameLog>,
+        ReadStorage<'a, Burden>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut hunger_clock, player_entity, runstate, mut inflict_damage, mut log) =
-            data;
+        let (
+            entities,
+            mut hunger_clock,
+            player_entity,
+            runstate,
+            mut inflict_damage,
+            mut log,
+            burdens,
+        ) = data;
 
         for (entity, mut clock) in (&entities, &mut hunger_clock).join() {
             let mut proceed = false;
@@ -41,7 +49,11 @@ impl<'a> System<'a> for HungerSystem {
             }
 
             if proceed {
-                clock.duration -= 1;
+                // Lugging too much around works up an appetite twice as fast
+                match burdens.get(entity) {
+                    Some(burden) if burden.is_overburdened() => clock.duration -= 2,
+                    _ => clock.duration -= 1,
+                }
                 if clock.duration < 1 {
                     match clock.state {
                         HungerState::WellFed => {
@@ -3,13 +3,15 @@ use specs::prelude::*;
 
 use crate::{
     components::{
-        named, Animation, AreaOfEffect, CombatStats, Confusion, Consumable, Equipable, Equipped,
-        GivenName, HungerClock, HungerState, InBackpack, InflictsDamage, MagicMapper, Name,
-        Position, ProvidesFood, ProvidesHealing, SufferDamage, WantsToDropItem, WantsToRemoveItem,
-        WantsToUseItem,
+        counted, named, Animation, AreaOfEffect, CombatStats, Confusion, Consumable, Corpse,
+        CurseRemover, Cursed, DefenceBonus, Equipable, Equipped, GivenName, HungerClock,
+        HungerState, Identifier, InBackpack, InflictsDamage, Lantern, MagicMapper, MeleePowerBonus,
+        Name, Position, ProvidesFood, ProvidesFuel, ProvidesHealing, Stack, SufferDamage,
+        WantsToDropItem, WantsToRemoveItem, WantsToUseItem,
     },
+    dungeon::MasterDungeonMap,
     gamelog::GameLog,
-    map::Map,
+    map::{DecalKind, Map},
     RunState,
 };
 
@@ -38,10 +40,24 @@ impl<'a> System<'a> for ItemUseSystem {
         WriteStorage<'a, InBackpack>,
         WriteExpect<'a, ParticleBuilder>,
         ReadStorage<'a, Position>,
-        ReadStorage<'a, ProvidesFood>,
+        (
+            ReadStorage<'a, ProvidesFood>,
+            ReadStorage<'a, Corpse>,
+            ReadStorage<'a, Identifier>,
+            WriteExpect<'a, MasterDungeonMap>,
+            WriteStorage<'a, Stack>,
+        ),
         WriteStorage<'a, HungerClock>,
-        ReadStorage<'a, MagicMapper>,
+        (
+            ReadStorage<'a, MagicMapper>,
+            ReadStorage<'a, CurseRemover>,
+            WriteStorage<'a, Cursed>,
+            WriteStorage<'a, MeleePowerBonus>,
+            WriteStorage<'a, DefenceBonus>,
+        ),
         WriteStorage<'a, Animation>,
+        ReadStorage<'a, ProvidesFuel>,
+        WriteStorage<'a, Lantern>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -66,13 +82,27 @@ impl<'a> System<'a> for ItemUseSystem {
             mut backpack,
             mut particle_builder,
             positions,
-            provides_food,
+            (provides_food, corpses, identifiers, mut dungeon, mut stacks),
             mut hunger_clocks,
-            magic_mapper,
+            (magic_mapper, curse_removers, mut cursed, mut power_bonuses, mut defence_bonuses),
             mut animations,
+            provides_fuel,
+            mut lanterns,
         ) = data;
 
         for (entity, useitem) in (&entities, &wants_drink).join() {
+            // Using something is the surest way to find out what it is
+            if entity == *player_entity {
+                if let Some(name) = names.get(useitem.item) {
+                    let disguise = dungeon.item_name(&name.name);
+                    if dungeon.identify(&name.name) {
+                        gamelog
+                            .entries
+                            .push(format!("The {} is a {}!", disguise, name.name));
+                    }
+                }
+            }
+
             let mut targets: Vec<Entity> = Vec::new();
             match useitem.target {
                 None => {
@@ -92,11 +122,16 @@ impl<'a> System<'a> for ItemUseSystem {
                             blast_tiles.retain(|p| {
                                 p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1
                             });
+                            // Blasts that do damage leave the ground scorched
+                            let scorches = inflict_damage.get(useitem.item).is_some();
                             for tile_idx in blast_tiles.iter() {
                                 let idx = map.xy_idx(tile_idx.x, tile_idx.y);
                                 for mob in map.tile_content[idx].iter() {
                                     targets.push(*mob);
                                 }
+                                if scorches {
+                                    map.add_decal(idx, DecalKind::Scorch);
+                                }
                                 particle_builder.request(
                                     tile_idx.x,
                                     tile_idx.y,
@@ -148,6 +183,44 @@ impl<'a> System<'a> for ItemUseSystem {
                         names.get(useitem.item).unwrap().name,
                     ));
                 }
+                if let Some(corpse) = corpses.get(useitem.item) {
+                    let rot_damage = if corpse.is_rotten() {
+                        Corpse::ROT_DAMAGE
+                    } else {
+                        0
+                    };
+                    if corpse.poison + rot_damage > 0 {
+                        SufferDamage::new_damage(
+                            &mut suffer_damage,
+                            target,
+                            corpse.poison + rot_damage,
+                        );
+                        if target == *player_entity {
+                            let reason = if rot_damage > 0 {
+                                "It had gone off"
+                            } else {
+                                "It was poisonous"
+                            };
+                            gamelog.entries.push(format!(
+                                "{}, you feel sick and lose {} hp.",
+                                reason,
+                                corpse.poison + rot_damage
+                            ));
+                        }
+                    }
+                }
+            }
+
+            if let Some(fuel) = provides_fuel.get(useitem.item) {
+                if let Some(lantern) = lanterns.get_mut(targets[0]) {
+                    lantern.fuel = i32::min(lantern.max_fuel, lantern.fuel + fuel.amount);
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You refill your lantern with the {}.",
+                            names.get(useitem.item).unwrap().name,
+                        ));
+                    }
+                }
             }
 
             if magic_mapper.get(useitem.item).is_some() {
@@ -167,6 +240,54 @@ impl<'a> System<'a> for ItemUseSystem {
                 *runstate = RunState::MagicMapReveal { row: 0, animation };
             }
 
+            if identifiers.get(useitem.item).is_some() {
+                let mut learnt = Vec::new();
+                for (pack, name) in (&backpack, &names).join() {
+                    if pack.owner == entity {
+                        let disguise = dungeon.item_name(&name.name);
+                        if dungeon.identify(&name.name) {
+                            learnt.push(format!("the {} is a {}", disguise, name.name));
+                        }
+                    }
+                }
+                if entity == *player_entity {
+                    if learnt.is_empty() {
+                        gamelog.entries.push("You learn nothing new.".to_string());
+                    } else {
+                        gamelog
+                            .entries
+                            .push(format!("You learn that {}.", learnt.join(", ")));
+                    }
+                }
+            }
+
+            if curse_removers.get(useitem.item).is_some() {
+                let lifted: Vec<Entity> = (&entities, &cursed, equipped.maybe(), backpack.maybe())
+                    .join()
+                    .filter(|(_, _, worn, carried)| {
+                        worn.map_or(false, |worn| worn.owner == entity)
+                            || carried.map_or(false, |carried| carried.owner == entity)
+                    })
+                    .map(|(item, _, _, _)| item)
+                    .collect();
+                // The curse turned the item's bonuses into penalties, so turn
+                // them back
+                for item in lifted.iter() {
+                    cursed.remove(*item);
+                    if let Some(bonus) = power_bonuses.get_mut(*item) {
+                        bonus.power = bonus.power.abs();
+                    }
+                    if let Some(bonus) = defence_bonuses.get_mut(*item) {
+                        bonus.defence = bonus.defence.abs();
+                    }
+                }
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push("You feel as if someone is watching over you.".to_string());
+                }
+            }
+
             if let Some(damage) = inflict_damage.get(useitem.item) {
                 for mob in targets.iter() {
                     SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
@@ -195,39 +316,67 @@ impl<'a> System<'a> for ItemUseSystem {
                 let target_slot = can_equip.slot;
                 let target = targets[0];
 
-                // Remove any items the target has in the item's slot
-                let mut to_unequip: Vec<Entity> = Vec::new();
-                for (item_entity, already_equipped, name) in (&entities, &equipped, &names).join() {
-                    if already_equipped.owner == target && already_equipped.slot == target_slot {
-                        to_unequip.push(item_entity);
-                        if target == *player_entity {
-                            gamelog.entries.push(format!("You unequip {}.", name.name));
+                // A cursed item in the slot won't make way
+                let stuck = (&equipped, &cursed, &names)
+                    .join()
+                    .find(|(already_equipped, _, _)| {
+                        already_equipped.owner == target && already_equipped.slot == target_slot
+                    })
+                    .map(|(_, _, name)| name.name.clone());
+                if let Some(stuck) = stuck {
+                    if target == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You can't take off the cursed {} to make room.",
+                            stuck
+                        ));
+                    }
+                } else {
+                    // Remove any items the target has in the item's slot
+                    let mut to_unequip: Vec<Entity> = Vec::new();
+                    for (item_entity, already_equipped, name) in
+                        (&entities, &equipped, &names).join()
+                    {
+                        if already_equipped.owner == target && already_equipped.slot == target_slot
+                        {
+                            to_unequip.push(item_entity);
+                            if target == *player_entity {
+                                gamelog.entries.push(format!("You unequip {}.", name.name));
+                            }
                         }
                     }
-                }
-                for item in to_unequip.iter() {
-                    equipped.remove(*item);
-                    backpack
-                        .insert(*item, InBackpack { owner: target })
-                        .expect("Unable to insert backpack entry");
-                }
+                    for item in to_unequip.iter() {
+                        equipped.remove(*item);
+                        backpack
+                            .insert(*item, InBackpack { owner: target })
+                            .expect("Unable to insert backpack entry");
+                    }
 
-                // Wield the item
-                equipped
-                    .insert(
-                        useitem.item,
-                        Equipped {
-                            owner: target,
-                            slot: target_slot,
-                        },
-                    )
-                    .expect("Unable to insert equipped component");
-                backpack.remove(useitem.item);
-                if target == *player_entity {
-                    gamelog.entries.push(format!(
-                        "You equip {}.",
-                        names.get(useitem.item).unwrap().name
-                    ));
+                    // Wield the item
+                    equipped
+                        .insert(
+                            useitem.item,
+                            Equipped {
+                                owner: target,
+                                slot: target_slot,
+                            },
+                        )
+                        .expect("Unable to insert equipped component");
+                    backpack.remove(useitem.item);
+                    if target == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You equip {}.",
+                            names.get(useitem.item).unwrap().name
+                        ));
+                    }
+                    if let Some(curse) = cursed.get_mut(useitem.item) {
+                        curse.known = true;
+                        if target == *player_entity {
+                            gamelog.entries.push(format!(
+                                "The {} is cursed, and won't come off!",
+                                names.get(useitem.item).unwrap().name
+                            ));
+                        }
+                    }
                 }
             }
 
@@ -264,7 +413,11 @@ impl<'a> System<'a> for ItemUseSystem {
             }
 
             if consumables.get(useitem.item).is_some() {
-                entities.delete(useitem.item).expect("Delete failed")
+                // Only one comes off a stack
+                match stacks.get_mut(useitem.item) {
+                    Some(stack) if stack.count > 1 => stack.count -= 1,
+                    _ => entities.delete(useitem.item).expect("Delete failed"),
+                }
             }
         }
 
@@ -283,6 +436,8 @@ impl<'a> System<'a> for ItemDropSystem {
         ReadStorage<'a, Name>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, InBackpack>,
+        ReadExpect<'a, MasterDungeonMap>,
+        ReadStorage<'a, Stack>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -294,6 +449,8 @@ impl<'a> System<'a> for ItemDropSystem {
             names,
             mut positions,
             mut backpack,
+            dungeon,
+            stacks,
         ) = data;
 
         for (entity, to_drop) in (&entities, &wants_drop).join() {
@@ -311,9 +468,10 @@ impl<'a> System<'a> for ItemDropSystem {
             backpack.remove(to_drop.item);
 
             if entity == *player_entity {
+                let name = dungeon.item_name(&names.get(to_drop.item).unwrap().name);
                 gamelog.entries.push(format!(
                     "You drop the {}.",
-                    names.get(to_drop.item).unwrap().name
+                    counted(name, stacks.get(to_drop.item))
                 ));
             }
         }
@@ -327,16 +485,38 @@ pub struct ItemRemoveSystem {}
 impl<'a> System<'a> for ItemRemoveSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
         Entities<'a>,
         WriteStorage<'a, WantsToRemoveItem>,
         WriteStorage<'a, Equipped>,
         WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Cursed>,
+        ReadStorage<'a, Name>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut wants_remove, mut equipped, mut backpack) = data;
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_remove,
+            mut equipped,
+            mut backpack,
+            cursed,
+            names,
+        ) = data;
 
         for (entity, to_remove) in (&entities, &wants_remove).join() {
+            if cursed.get(to_remove.item).is_some() {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!(
+                        "You can't remove the {}, it's cursed!",
+                        names.get(to_remove.item).unwrap().name
+                    ));
+                }
+                continue;
+            }
             equipped.remove(to_remove.item);
             backpack
                 .insert(to_remove.item, InBackpack { owner: entity })
@@ -1,45 +1,146 @@
 use specs::prelude::*;
 
 use crate::{
-    components::{InBackpack, Name, Position, WantsToPickupItem},
+    components::{
+        counted, stack_weight, Burden, Gold, InBackpack, Name, Position, Stack, Wallet,
+        WantsToPickupItem, Weight,
+    },
+    dungeon::MasterDungeonMap,
     gamelog::GameLog,
 };
 
 pub struct ItemCollectionSystem {}
 
 impl<'a> System<'a> for ItemCollectionSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        Entities<'a>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        ReadExpect<'a, MasterDungeonMap>,
+        WriteStorage<'a, Stack>,
+        ReadStorage<'a, Gold>,
+        WriteStorage<'a, Wallet>,
+        WriteStorage<'a, Burden>,
+        ReadStorage<'a, Weight>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            dungeon,
+            mut stacks,
+            gold,
+            mut wallets,
+            mut burdens,
+            weights,
+        ) = data;
 
         for pickup in wants_pickup.join() {
-            positions.remove(pickup.item);
-            backpack
-                .insert(
-                    pickup.item,
-                    InBackpack {
-                        owner: pickup.collected_by,
-                    },
-                )
-                .expect("Unable to insert backpack entry");
+            // Gold goes in the wallet, not the pack
+            if let Some(gold) = gold.get(pickup.item) {
+                if let Some(wallet) = wallets.get_mut(pickup.collected_by) {
+                    wallet.gold += gold.amount;
+                    if pickup.collected_by == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("You pick up {} gold.", gold.amount));
+                    }
+                    entities.delete(pickup.item).expect("Delete failed");
+                }
+                continue;
+            }
+
+            let name = counted(
+                dungeon.item_name(&names.get(pickup.item).unwrap().name),
+                stacks.get(pickup.item),
+            );
+            let weight = stack_weight(weights.get(pickup.item), stacks.get(pickup.item));
+            if let Some(burden) = burdens.get_mut(pickup.collected_by) {
+                if !burden.can_carry(weight) {
+                    if pickup.collected_by == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("The {} is too heavy to carry.", name));
+                    }
+                    continue;
+                }
+                // Counted now, so the cap holds until the next update
+                burden.carried += weight;
+            }
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!(
-                    "You pick up the {}.",
-                    names.get(pickup.item).unwrap().name
-                ));
+                gamelog.entries.push(format!("You pick up the {}.", name));
             }
+
+            positions.remove(pickup.item);
+            stow(
+                &entities,
+                &mut backpack,
+                &names,
+                &mut stacks,
+                pickup.collected_by,
+                pickup.item,
+            );
         }
 
         wants_pickup.clear();
     }
 }
+
+/// Puts an item, which isn't anywhere else, in `owner`'s pack. Stackable
+/// items join a pile of the same thing, if there is one.
+pub fn stow(
+    entities: &Entities,
+    backpack: &mut WriteStorage<InBackpack>,
+    names: &ReadStorage<Name>,
+    stacks: &mut WriteStorage<Stack>,
+    owner: Entity,
+    item: Entity,
+) {
+    let pile = match (stacks.get(item), names.get(item)) {
+        (Some(_), Some(name)) => (&*entities, &*backpack, names, &*stacks)
+            .join()
+            .find(|(pile, pack, pile_name, _)| {
+                *pile != item && pack.owner == owner && pile_name.name == name.name
+            })
+            .map(|(pile, _, _, _)| pile),
+        _ => None,
+    };
+    match pile {
+        Some(pile) => {
+            let count = stacks.get(item).map_or(1, |stack| stack.count);
+            if let Some(stack) = stacks.get_mut(pile) {
+                stack.count += count;
+            }
+            entities.delete(item).expect("Delete failed");
+        }
+        None => {
+            backpack
+                .insert(item, InBackpack { owner })
+                .expect("Unable to insert backpack entry");
+        }
+    }
+}
+
+/// `stow`, for use outside of a system
+pub fn stow_item(ecs: &mut World, owner: Entity, item: Entity) {
+    stow(
+        &ecs.entities(),
+        &mut ecs.write_storage::<InBackpack>(),
+        &ecs.read_storage::<Name>(),
+        &mut ecs.write_storage::<Stack>(),
+        owner,
+        item,
+    );
+}
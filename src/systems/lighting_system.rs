@@ -0,0 +1,97 @@
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+use crate::{
+    components::{Lantern, LightSource, Position, Viewshed},
+    gamelog::GameLog,
+    map::Map,
+    RunState,
+};
+
+/// Burns lantern fuel and works out how much light falls on every tile
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, LightSource>,
+        WriteStorage<'a, Lantern>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut map,
+            player_entity,
+            runstate,
+            mut gamelog,
+            entities,
+            positions,
+            light_sources,
+            mut lanterns,
+            mut viewsheds,
+        ) = data;
+
+        if *runstate == RunState::PlayerTurn {
+            for (entity, lantern) in (&entities, &mut lanterns).join() {
+                if lantern.fuel <= 0 {
+                    continue;
+                }
+                lantern.fuel -= 1;
+                if entity != *player_entity {
+                    continue;
+                }
+                if lantern.fuel == Lantern::LOW_FUEL {
+                    gamelog
+                        .entries
+                        .push("Your lantern flickers, it is running low on oil.".to_string());
+                } else if lantern.fuel == 0 {
+                    gamelog.entries.push("Your lantern goes out.".to_string());
+                }
+            }
+        }
+
+        let mut light = vec![RGB::from_f32(0., 0., 0.); map.tiles.len()];
+        for (entity, pos, source) in (&entities, &positions, &light_sources).join() {
+            let range = match lanterns.get(entity) {
+                Some(lantern) => lantern.range(source.range),
+                None => source.range,
+            };
+            if range < 1 {
+                continue;
+            }
+
+            let origin = Point::new(pos.x, pos.y);
+            for tile in field_of_view(origin, range, &*map).iter() {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+                    continue;
+                }
+                let distance = DistanceAlg::Pythagoras.distance2d(origin, *tile);
+                let intensity = 1. - distance / (range as f32 + 1.);
+                if intensity <= 0. {
+                    continue;
+                }
+                let lit = &mut light[map.xy_idx(tile.x, tile.y)];
+                *lit = RGB::from_f32(
+                    f32::min(1., lit.r + source.colour.r * intensity),
+                    f32::min(1., lit.g + source.colour.g * intensity),
+                    f32::min(1., lit.b + source.colour.b * intensity),
+                );
+            }
+        }
+
+        // Lights moving around change what the player can see, even standing still
+        if light != map.light {
+            map.light = light;
+            if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
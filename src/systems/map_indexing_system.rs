@@ -1,5 +1,5 @@
 use crate::{
-    components::{BlocksTile, Position},
+    components::{BlocksTile, BlocksVisibility, Position},
     map::Map,
 };
 use specs::prelude::*;
@@ -11,14 +11,16 @@ impl<'a> System<'a> for MapIndexingSystem {
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksTile>,
+        ReadStorage<'a, BlocksVisibility>,
         Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, view_blockers, entities) = data;
 
         map.populate_blocked();
         map.clear_content_index();
+        map.view_blocked.clear();
         for (entity, position) in (&entities, &position).join() {
             let idx = map.xy_idx(position.x, position.y);
 
@@ -26,6 +28,9 @@ impl<'a> System<'a> for MapIndexingSystem {
             if let Some(_) = p {
                 map.blocked[idx] = true;
             }
+            if view_blockers.get(entity).is_some() {
+                map.view_blocked.insert(idx);
+            }
 
             map.tile_content[idx].push(entity);
         }
@@ -9,7 +9,11 @@ use crate::{
         MeleePowerBonus, Name, Position, SufferDamage, WantsToMelee,
     },
 };
-use crate::{components::MeleeType, gamelog::GameLog};
+use crate::{
+    components::MeleeType,
+    gamelog::GameLog,
+    rng::{GameRng, RngStream},
+};
 
 use super::ParticleBuilder;
 
@@ -31,7 +35,7 @@ impl<'a> System<'a> for MeleeCombatSystem {
         ReadStorage<'a, Position>,
         ReadStorage<'a, HungerClock>,
         WriteExpect<'a, SoundResource>,
-        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameRng>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -132,7 +136,7 @@ impl<'a> System<'a> for MeleeCombatSystem {
         // TODO make sound effect system instead
         if melee_was_had {
             match sounds.play_sound(
-                melee_sound(&mut rng, melee_type),
+                melee_sound(rng.stream(RngStream::Sound), melee_type),
                 InstanceSettings::default(),
             ) {
                 Ok(_) => {}
@@ -1,23 +1,33 @@
+mod corpse_system;
 mod damage_system;
+mod decal_system;
+mod encumbrance_system;
 mod hunger_system;
 mod inventory_system;
 mod item_collection_system;
+mod lighting_system;
 mod map_indexing_system;
 mod melee_combat_system;
 mod monster_ai_system;
 mod music_system;
 mod particle_system;
+mod terrain_system;
 mod trigger_system;
 mod visibility_system;
 
+pub use corpse_system::*;
 pub use damage_system::*;
+pub use decal_system::*;
+pub use encumbrance_system::*;
 pub use hunger_system::*;
 pub use inventory_system::*;
 pub use item_collection_system::*;
+pub use lighting_system::*;
 pub use map_indexing_system::*;
 pub use melee_combat_system::*;
 pub use monster_ai_system::*;
 pub use music_system::*;
 pub use particle_system::*;
+pub use terrain_system::*;
 pub use trigger_system::*;
 pub use visibility_system::*;
@@ -4,9 +4,13 @@ use specs::prelude::*;
 
 use crate::{
     audio::SoundResource,
-    components::{Confusion, EntityMoved, Monster, MonsterType, Position, Viewshed, WantsToMelee},
+    components::{
+        BlocksTile, BlocksVisibility, Confusion, Door, EntityMoved, Hidden, Monster, MonsterType,
+        Position, Renderable, Viewshed, WantsToMelee,
+    },
     map::Map,
-    RunState,
+    rng::{GameRng, RngStream},
+    spawner, RunState,
 };
 
 use super::ParticleBuilder;
@@ -29,7 +33,12 @@ impl<'a> System<'a> for MonsterAI {
         WriteExpect<'a, ParticleBuilder>,
         WriteStorage<'a, EntityMoved>,
         WriteExpect<'a, SoundResource>,
-        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameRng>,
+        WriteStorage<'a, Door>,
+        WriteStorage<'a, BlocksTile>,
+        WriteStorage<'a, BlocksVisibility>,
+        WriteStorage<'a, Renderable>,
+        ReadStorage<'a, Hidden>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -48,12 +57,26 @@ impl<'a> System<'a> for MonsterAI {
             mut entity_moved,
             mut sounds,
             mut rng,
+            mut doors,
+            mut blocks_movement,
+            mut blocks_visibility,
+            mut renderables,
+            hidden,
         ) = data;
 
         if *runstate != RunState::MonsterTurn {
             return;
         }
 
+        // Nobody knows about secret doors until the player finds them
+        let mut closed_doors: Vec<usize> = Vec::new();
+        for (door, pos, _) in (&doors, &position, !&hidden).join() {
+            if !door.open {
+                closed_doors.push(map.xy_idx(pos.x, pos.y));
+            }
+        }
+        let mut opened_door = false;
+
         for (entity, mut viewshed, mut monster, mut pos) in
             (&entities, &mut viewshed, &mut monster, &mut position).join()
         {
@@ -91,28 +114,72 @@ impl<'a> System<'a> for MonsterAI {
                         .expect("Unable to insert attack");
                     return;
                 } else if viewshed.visible_tiles.contains(&*player_pos) {
+                    // Monsters that can open doors path straight through them
+                    let through_doors = monster.monster_type.can_open_doors();
+                    if through_doors {
+                        for idx in closed_doors.iter() {
+                            map.blocked[*idx] = false;
+                        }
+                    }
                     let path = a_star_search(
                         map.xy_idx(pos.x, pos.y) as i32,
                         map.xy_idx(player_pos.x, player_pos.y) as i32,
                         &mut *map,
                     );
-                    if path.success && path.steps.len() > 1 {
+                    if through_doors {
+                        for idx in closed_doors.iter() {
+                            map.blocked[*idx] = true;
+                        }
+                    }
+
+                    let next_step = if path.success && path.steps.len() > 1 {
+                        Some(path.steps[1])
+                    } else {
+                        None
+                    };
+                    let door_in_the_way = next_step.and_then(|step| {
+                        if !closed_doors.contains(&step) {
+                            return None;
+                        }
+                        map.tile_content[step]
+                            .iter()
+                            .find(|e| doors.get(**e).is_some())
+                            .map(|door_entity| (step, *door_entity))
+                    });
+
+                    if let Some((idx, door_entity)) = door_in_the_way {
+                        // Opening the door takes the whole turn
+                        spawner::set_door_open(
+                            door_entity,
+                            true,
+                            &mut doors,
+                            &mut blocks_movement,
+                            &mut blocks_visibility,
+                            &mut renderables,
+                        );
+                        map.blocked[idx] = false;
+                        map.view_blocked.remove(&idx);
+                        closed_doors.retain(|door_idx| *door_idx != idx);
+                        viewshed.dirty = true;
+                        opened_door = true;
+                    } else if let Some(step) = next_step {
+                        let from = Point::new(pos.x, pos.y);
                         let mut idx = map.xy_idx(pos.x, pos.y);
                         map.blocked[idx] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
+                        pos.x = step as i32 % map.width;
+                        pos.y = step as i32 / map.width;
                         idx = map.xy_idx(pos.x, pos.y);
                         map.blocked[idx] = true;
                         viewshed.dirty = true;
                         entity_moved
-                            .insert(entity, EntityMoved {})
+                            .insert(entity, EntityMoved { from })
                             .expect("Unable to insert marker");
                     }
 
                     if !monster.seen_player {
                         monster.seen_player = true;
                         match sounds.play_sound(
-                            monster_noise(&mut rng, monster.monster_type),
+                            monster_noise(rng.stream(RngStream::Sound), monster.monster_type),
                             InstanceSettings::default(),
                         ) {
                             Ok(_) => {}
@@ -122,6 +189,13 @@ impl<'a> System<'a> for MonsterAI {
                 }
             }
         }
+
+        // The player may be able to see through the door that was opened
+        if opened_door {
+            if let Some(player_viewshed) = viewshed.get_mut(*player_entity) {
+                player_viewshed.dirty = true;
+            }
+        }
     }
 }
 
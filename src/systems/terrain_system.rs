@@ -0,0 +1,109 @@
+use specs::prelude::*;
+
+use crate::{
+    components::{
+        named, CombatStats, EntityMoved, Equipable, GivenName, InBackpack, Name, Position,
+        SufferDamage,
+    },
+    gamelog::GameLog,
+    map::{Map, TileType},
+    RunState,
+};
+
+const LAVA_DAMAGE: i32 = 5;
+
+/// Applies what the ground does to whoever is standing on it
+pub struct TerrainSystem {}
+
+impl<'a> System<'a> for TerrainSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Equipable>,
+        WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, GivenName>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            player_entity,
+            mut runstate,
+            mut gamelog,
+            entities,
+            entity_moved,
+            mut positions,
+            combat_stats,
+            mut suffer_damage,
+            equipable,
+            mut backpack,
+            names,
+            given_names,
+        ) = data;
+
+        // Lava burns once a round, for as long as you stand in it
+        if *runstate == RunState::MonsterTurn {
+            for (entity, pos, _stats) in (&entities, &positions, &combat_stats).join() {
+                let idx = map.xy_idx(pos.x, pos.y);
+                if map.tiles[idx] != TileType::Lava {
+                    continue;
+                }
+                SufferDamage::new_damage(&mut suffer_damage, entity, LAVA_DAMAGE);
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push(format!("The lava burns you for {} damage!", LAVA_DAMAGE));
+                } else if map.visible_tiles[idx] {
+                    gamelog.entries.push(format!(
+                        "{} is burned by the lava.",
+                        named(names.get(entity), given_names.get(entity))
+                    ));
+                }
+            }
+        }
+
+        let mut swimmers: Vec<(Entity, Position)> = Vec::new();
+        for (entity, _moved, pos) in (&entities, &entity_moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            match map.tiles[idx] {
+                TileType::DeepWater => swimmers.push((entity, pos.clone())),
+                TileType::Chasm if entity == *player_entity => {
+                    gamelog.entries.push("You fall into the chasm!".to_string());
+                    *runstate = RunState::NextLevel;
+                }
+                _ => {}
+            }
+        }
+
+        // Heavy gear has to be let go of to stay afloat
+        for (swimmer, pos) in swimmers.iter() {
+            let heavy_items: Vec<Entity> = (&entities, &equipable, &backpack)
+                .join()
+                .filter(|(_, _, in_backpack)| in_backpack.owner == *swimmer)
+                .map(|(item, _, _)| item)
+                .collect();
+            for item in heavy_items {
+                backpack.remove(item);
+                positions
+                    .insert(item, pos.clone())
+                    .expect("Unable to insert position");
+                if *swimmer == *player_entity {
+                    if let Some(name) = names.get(item) {
+                        gamelog
+                            .entries
+                            .push(format!("You let go of your {} to stay afloat.", name.name));
+                    }
+                }
+            }
+        }
+    }
+}
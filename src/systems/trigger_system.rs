@@ -10,6 +10,7 @@ use crate::{
     },
     gamelog::GameLog,
     map::Map,
+    rng::{GameRng, RngStream},
 };
 
 use super::ParticleBuilder;
@@ -32,7 +33,7 @@ impl<'a> System<'a> for TriggerSystem {
         Entities<'a>,
         WriteExpect<'a, GameLog>,
         WriteExpect<'a, SoundResource>,
-        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameRng>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -81,9 +82,10 @@ impl<'a> System<'a> for TriggerSystem {
                             log.entries
                                 .push(format!("{} suffers {} damage.", fool_title, damage.damage));
 
-                            match sounds
-                                .play_sound(trap_sound(&mut rng), InstanceSettings::default())
-                            {
+                            match sounds.play_sound(
+                                trap_sound(rng.stream(RngStream::Sound)),
+                                InstanceSettings::default(),
+                            ) {
                                 Ok(_) => {}
                                 Err(e) => console::log(format!("Unable to play sound: {}", e)),
                             }
@@ -3,9 +3,11 @@ use bracket_lib::prelude::*;
 use specs::prelude::*;
 
 use crate::{
-    components::{Hidden, Name, Player, Position, Viewshed},
+    audio::{DesireMusic, Music},
+    components::{named, Door, GivenName, Hidden, Name, Player, Position, Unique, Viewshed},
     gamelog::GameLog,
-    map::Map,
+    map::{Map, TileType},
+    rng::{GameRng, RngStream},
 };
 
 pub struct VisibilitySystem {}
@@ -18,14 +20,31 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
         WriteStorage<'a, Hidden>,
-        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameRng>,
         WriteExpect<'a, GameLog>,
         ReadStorage<'a, Name>,
+        ReadStorage<'a, Door>,
+        WriteStorage<'a, Unique>,
+        ReadStorage<'a, GivenName>,
+        WriteExpect<'a, DesireMusic>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player, mut hidden, mut rng, mut gamelog, names) =
-            data;
+        let (
+            mut map,
+            entities,
+            mut viewshed,
+            pos,
+            player,
+            mut hidden,
+            mut rng,
+            mut gamelog,
+            names,
+            doors,
+            mut uniques,
+            given_names,
+            mut desire_music,
+        ) = data;
 
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty == true {
@@ -38,6 +57,13 @@ impl<'a> System<'a> for VisibilitySystem {
                     .retain(|p| p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height);
 
                 if player.get(ent).is_some() {
+                    // The player can only make out what is lit, or right next to them
+                    let origin = Point::new(pos.x, pos.y);
+                    viewshed.visible_tiles.retain(|p| {
+                        DistanceAlg::Pythagoras.distance2d(origin, *p) < 1.5
+                            || map.is_lit(map.xy_idx(p.x, p.y))
+                    });
+
                     for t in map.visible_tiles.iter_mut() {
                         *t = false
                     }
@@ -47,15 +73,32 @@ impl<'a> System<'a> for VisibilitySystem {
                         map.visible_tiles[idx] = true;
 
                         // Chance to reveal hidden stuff
+                        let spotted: Vec<Entity> = map.tile_content[idx]
+                            .iter()
+                            .filter(|e| hidden.get(**e).is_some())
+                            .filter(|_| rng.stream(RngStream::Discovery).roll_dice(1, 24) == 1)
+                            .copied()
+                            .collect();
+                        for e in spotted {
+                            reveal_hidden(
+                                &mut map,
+                                idx,
+                                e,
+                                &mut hidden,
+                                &doors,
+                                &names,
+                                &mut gamelog,
+                            );
+                        }
+
+                        // The first sight of a unique gets a fanfare
                         for e in map.tile_content[idx].iter() {
-                            if hidden.get(*e).is_some() {
-                                if rng.roll_dice(1, 24) == 1 {
-                                    if let Some(name) = names.get(*e) {
-                                        gamelog
-                                            .entries
-                                            .push(format!("You spotted a {}!", &name.name))
-                                    }
-                                    hidden.remove(*e);
+                            if let Some(unique) = uniques.get_mut(*e) {
+                                if !unique.spotted {
+                                    unique.spotted = true;
+                                    desire_music.music = Some(Music::Boss);
+                                    let title = named(names.get(*e), given_names.get(*e));
+                                    gamelog.entries.push(format!("You spot {}!", title));
                                 }
                             }
                         }
@@ -65,3 +108,25 @@ impl<'a> System<'a> for VisibilitySystem {
         }
     }
 }
+
+/// Brings a hidden entity on a tile to light. A secret door turns the wall it
+/// is set in into a doorway.
+pub fn reveal_hidden(
+    map: &mut Map,
+    idx: usize,
+    entity: Entity,
+    hidden: &mut WriteStorage<Hidden>,
+    doors: &ReadStorage<Door>,
+    names: &ReadStorage<Name>,
+    gamelog: &mut GameLog,
+) {
+    hidden.remove(entity);
+    if doors.get(entity).is_some() {
+        map.tiles[idx] = TileType::Floor;
+        gamelog.entries.push("You found a secret door!".to_string());
+    } else if let Some(name) = names.get(entity) {
+        gamelog
+            .entries
+            .push(format!("You spotted a {}!", &name.name));
+    }
+}
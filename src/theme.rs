@@ -0,0 +1,134 @@
+use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The look of a band of depths. Every level picks its theme when it is
+/// generated, and keeps it when saved.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum Theme {
+    Mines,
+    Crypt,
+    Caverns,
+    Hell,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::Mines
+    }
+}
+
+const ALL_THEMES: &[Theme] = &[Theme::Mines, Theme::Crypt, Theme::Caverns, Theme::Hell];
+
+impl Theme {
+    pub fn for_depth(depth: i32) -> Theme {
+        match depth {
+            i32::MIN..=3 => Theme::Mines,
+            4..=6 => Theme::Crypt,
+            7..=9 => Theme::Caverns,
+            _ => Theme::Hell,
+        }
+    }
+
+    /// The name of the area, as shown next to the depth
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Mines => "Mines",
+            Theme::Crypt => "Crypt",
+            Theme::Caverns => "Caverns",
+            Theme::Hell => "Hell",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        ALL_THEMES
+            .iter()
+            .copied()
+            .find(|theme| theme.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The glyph for a wall, given which sides have revealed walls next to it
+    /// as a bitmask of north 1, south 2, west 4 and east 8
+    pub fn wall_glyph(self, mask: u8) -> FontCharType {
+        match self {
+            // Timber supports
+            Theme::Mines => match mask {
+                0 => 9,
+                1 | 2 | 3 => 179,
+                4 | 8 | 12 => 196,
+                5 => 217,
+                6 => 191,
+                7 => 180,
+                9 => 192,
+                10 => 218,
+                11 => 195,
+                13 => 193,
+                14 => 194,
+                _ => 197,
+            },
+            // Dressed stone
+            Theme::Crypt => match mask {
+                0 => 9,
+                1 | 2 | 3 => 186,
+                4 | 8 | 12 => 205,
+                5 => 188,
+                6 => 187,
+                7 => 185,
+                9 => 200,
+                10 => 201,
+                11 => 204,
+                13 => 202,
+                14 => 203,
+                _ => 206,
+            },
+            // Raw rock doesn't line up with anything
+            Theme::Caverns => 178,
+            Theme::Hell => 177,
+        }
+    }
+
+    pub fn wall_colours(self) -> (RGB, RGB) {
+        match self {
+            Theme::Mines => (RGB::from_f32(0.6, 0.45, 0.25), RGB::from_f32(0., 0., 0.)),
+            Theme::Crypt => (RGB::from_f32(0.6, 0.6, 0.7), RGB::from_f32(0.05, 0.05, 0.1)),
+            Theme::Caverns => (
+                RGB::from_f32(0.25, 0.55, 0.4),
+                RGB::from_f32(0., 0.05, 0.05),
+            ),
+            Theme::Hell => (RGB::from_f32(0.7, 0.15, 0.05), RGB::from_f32(0.15, 0., 0.)),
+        }
+    }
+
+    pub fn floor_colours(self) -> (RGB, RGB) {
+        match self {
+            Theme::Mines => (RGB::from_f32(0.5, 0.4, 0.3), RGB::from_f32(0., 0., 0.)),
+            Theme::Crypt => (RGB::from_f32(0.4, 0.4, 0.5), RGB::from_f32(0., 0., 0.)),
+            Theme::Caverns => (RGB::from_f32(0., 0.5, 0.5), RGB::from_f32(0., 0., 0.)),
+            Theme::Hell => (RGB::from_f32(0.6, 0.3, 0.1), RGB::from_f32(0.08, 0., 0.)),
+        }
+    }
+
+    /// Floors are mostly plain, with the odd pebble or crack thrown in
+    pub fn floor_glyph(self, idx: usize) -> FontCharType {
+        let glyphs: &[char] = match self {
+            Theme::Mines => &['.', '.', '.', '.', ','],
+            Theme::Crypt => &['.'],
+            Theme::Caverns => &['.', '.', '.', ',', '\'', '`'],
+            Theme::Hell => &['.', '.', '\'', '`'],
+        };
+        // A hash of the position, so the pattern stays put between frames
+        let hash = (idx as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+        to_cp437(glyphs[hash as usize % glyphs.len()])
+    }
+
+    /// How a colour looks on a tile that is remembered rather than seen
+    pub fn remembered(self, colour: RGB) -> RGB {
+        let grey = colour.to_greyscale();
+        let tint = match self {
+            Theme::Mines => RGB::from_f32(0.8, 0.8, 0.8),
+            Theme::Crypt => RGB::from_f32(0.6, 0.6, 0.75),
+            Theme::Caverns => RGB::from_f32(0.55, 0.7, 0.7),
+            Theme::Hell => RGB::from_f32(0.75, 0.5, 0.45),
+        };
+        RGB::from_f32(grey.r * tint.r, grey.g * tint.g, grey.b * tint.b)
+    }
+}